@@ -1,14 +1,40 @@
-use std::process::ExitStatus;
+use std::{
+    collections::BTreeMap, fmt::Display, path::PathBuf, process::ExitStatus, time::Duration,
+};
 
-use serde::Deserialize;
+use regex::{Regex, RegexBuilder};
+use rust_metadata::cfg::CfgExpr;
+use serde::{Deserialize, Deserializer};
 
-use crate::struct_merge::StructMerge;
+use crate::{
+    output_assertions::{one_or_many, OutputAssertions},
+    output_filters::OutputFilter,
+    run_conditions::RunConditions,
+    struct_merge::StructMerge,
+};
 
 #[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ConfigArgs {
     pub label: Option<String>,
     pub arguments: Option<Vec<String>>,
     pub expected_exit_status: Option<ExpectedExitStatus>,
+    pub timeout: Option<HumanDuration>,
+    pub env: Option<BTreeMap<String, String>>,
+    pub env_remove: Option<Vec<String>>,
+    pub cwd: Option<PathBuf>,
+    pub stdin: Option<StdinInput>,
+    pub expected_stdout: Option<OutputAssertions>,
+    pub expected_stderr: Option<OutputAssertions>,
+    pub snapshot: Option<bool>,
+    pub filters: Option<Vec<OutputFilter>>,
+    pub tags: Option<Vec<String>>,
+    pub slots: Option<u32>,
+    pub exclusive_group: Option<String>,
+    pub retries: Option<u32>,
+    pub xfail: Option<String>,
+    pub skip_if: Option<RunConditions>,
+    pub only_if: Option<RunConditions>,
+    pub only_on: Option<CfgExpression>,
 }
 
 impl ConfigArgs {
@@ -17,6 +43,23 @@ impl ConfigArgs {
             label: self.label,
             arguments: self.arguments.unwrap_or_default(),
             expected_exit_status: self.expected_exit_status.unwrap_or_default(),
+            timeout: self.timeout.map(|timeout| timeout.0),
+            env: self.env.unwrap_or_default(),
+            env_remove: self.env_remove.unwrap_or_default(),
+            cwd: self.cwd,
+            stdin: self.stdin,
+            expected_stdout: self.expected_stdout,
+            expected_stderr: self.expected_stderr,
+            snapshot: self.snapshot.unwrap_or_default(),
+            filters: self.filters.unwrap_or_default(),
+            tags: self.tags.unwrap_or_default(),
+            slots: self.slots.unwrap_or(1),
+            exclusive_group: self.exclusive_group,
+            retries: self.retries.unwrap_or_default(),
+            xfail: self.xfail,
+            skip_if: self.skip_if,
+            only_if: self.only_if,
+            only_on: self.only_on,
         }
     }
 }
@@ -43,6 +86,16 @@ impl Default for ExpectedExitStatus {
     }
 }
 
+impl Display for ExpectedExitStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpectedExitStatus::Group(ExitStatusGroup::Success) => write!(f, "success"),
+            ExpectedExitStatus::Group(ExitStatusGroup::Failure) => write!(f, "failure"),
+            ExpectedExitStatus::Code(code) => write!(f, "exit status: {code}"),
+        }
+    }
+}
+
 impl ExpectedExitStatus {
     pub fn matches(&self, exit_status: &ExitStatus) -> bool {
         match self {
@@ -53,6 +106,134 @@ impl ExpectedExitStatus {
     }
 }
 
+/// Input to write into the stdin of an example
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(untagged)]
+pub enum StdinInput {
+    Inline(String),
+    /// A file relative to the directory of the package `Cargo.toml`
+    File {
+        file: PathBuf,
+    },
+}
+
+/// A duration in a human readable format like `30s` or `1m 30s`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HumanDuration(pub Duration);
+
+impl<'de> Deserialize<'de> for HumanDuration {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let duration = String::deserialize(deserializer)?;
+        humantime::parse_duration(&duration)
+            .map(Self)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// A `cfg(..)` expression like `cfg(all(unix, target_pointer_width = "64"))`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CfgExpression(pub CfgExpr);
+
+impl<'de> Deserialize<'de> for CfgExpression {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let expression = String::deserialize(deserializer)?;
+        CfgExpr::parse(&expression).map(Self).map_err(|error| {
+            serde::de::Error::custom(format!(
+                "invalid cfg expression `{expression}`: {}",
+                error.reason()
+            ))
+        })
+    }
+}
+
+/// A regex compiled when the configuration is read, so invalid patterns are rejected up front
+#[derive(Debug, Clone)]
+pub struct ConfigRegex(pub Regex);
+
+impl ConfigRegex {
+    fn new<E: serde::de::Error>(pattern: &str, multi_line: bool) -> Result<Self, E> {
+        RegexBuilder::new(pattern)
+            .multi_line(multi_line)
+            .build()
+            .map(Self)
+            .map_err(|error| E::custom(format!("invalid regex `{pattern}`: {error}")))
+    }
+}
+
+impl<'de> Deserialize<'de> for ConfigRegex {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Self::new(&String::deserialize(deserializer)?, false)
+    }
+}
+
+/// One or many regexes where `^` and `$` match at the start and end of every line
+pub fn multi_line_regexes<'de, D>(deserializer: D) -> Result<Vec<ConfigRegex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    one_or_many(deserializer)?
+        .iter()
+        .map(|pattern| ConfigRegex::new(pattern, true))
+        .collect()
+}
+
+/// Deserializes a value that is either of two shapes, with `first` if `is_first` holds for it and
+/// with `second` otherwise
+// Not `#[serde(untagged)]`, which would replace the errors of the shapes with a generic one
+pub fn deserialize_either<'de, D, T>(
+    deserializer: D,
+    is_first: fn(&serde_json::Value) -> bool,
+    first: impl FnOnce(serde_json::Value) -> Result<T, serde_json::Error>,
+    second: impl FnOnce(serde_json::Value) -> Result<T, serde_json::Error>,
+) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    if is_first(&value) {
+        first(value)
+    } else {
+        second(value)
+    }
+    .map_err(serde::de::Error::custom)
+}
+
+// Regexes are compared by their pattern, so configurations stay comparable
+impl PartialEq for ConfigRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for ConfigRegex {}
+
+impl PartialOrd for ConfigRegex {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ConfigRegex {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.as_str().cmp(other.0.as_str())
+    }
+}
+
+impl std::hash::Hash for ConfigRegex {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.as_str().hash(state);
+    }
+}
+
 impl StructMerge for ConfigArgs {
     fn join_inplace(&mut self, other: Self) {
         if self.arguments.is_none() {
@@ -61,6 +242,86 @@ impl StructMerge for ConfigArgs {
         if self.expected_exit_status.is_none() {
             self.expected_exit_status = other.expected_exit_status
         }
+        if self.timeout.is_none() {
+            self.timeout = other.timeout;
+        }
+        match (&mut self.env, other.env) {
+            (Some(env), Some(other_env)) => {
+                for (key, value) in other_env {
+                    env.entry(key).or_insert(value);
+                }
+            }
+            (env @ None, other_env) => *env = other_env,
+            (Some(_), None) => {}
+        }
+        match (&mut self.env_remove, other.env_remove) {
+            (Some(env_remove), Some(other_env_remove)) => {
+                for key in other_env_remove {
+                    if !env_remove.contains(&key) {
+                        env_remove.push(key);
+                    }
+                }
+            }
+            (env_remove @ None, other_env_remove) => *env_remove = other_env_remove,
+            (Some(_), None) => {}
+        }
+        if self.cwd.is_none() {
+            self.cwd = other.cwd;
+        }
+        if self.stdin.is_none() {
+            self.stdin = other.stdin;
+        }
+        if self.expected_stdout.is_none() {
+            self.expected_stdout = other.expected_stdout;
+        }
+        if self.expected_stderr.is_none() {
+            self.expected_stderr = other.expected_stderr;
+        }
+        if self.snapshot.is_none() {
+            self.snapshot = other.snapshot;
+        }
+        match (&mut self.filters, other.filters) {
+            // Filters are applied in order, so the more general ones of the template go first
+            (Some(filters), Some(mut other_filters)) => {
+                other_filters.retain(|filter| !filters.contains(filter));
+                other_filters.append(filters);
+                *filters = other_filters;
+            }
+            (filters @ None, other_filters) => *filters = other_filters,
+            (Some(_), None) => {}
+        }
+        if self.slots.is_none() {
+            self.slots = other.slots;
+        }
+        if self.exclusive_group.is_none() {
+            self.exclusive_group = other.exclusive_group;
+        }
+        if self.retries.is_none() {
+            self.retries = other.retries;
+        }
+        if self.xfail.is_none() {
+            self.xfail = other.xfail;
+        }
+        if self.skip_if.is_none() {
+            self.skip_if = other.skip_if;
+        }
+        if self.only_if.is_none() {
+            self.only_if = other.only_if;
+        }
+        if self.only_on.is_none() {
+            self.only_on = other.only_on;
+        }
+        match (&mut self.tags, other.tags) {
+            (Some(tags), Some(other_tags)) => {
+                for tag in other_tags {
+                    if !tags.contains(&tag) {
+                        tags.push(tag);
+                    }
+                }
+            }
+            (tags @ None, other_tags) => *tags = other_tags,
+            (Some(_), None) => {}
+        }
     }
 }
 
@@ -69,4 +330,143 @@ pub struct FinalizedConfigArgs {
     pub label: Option<String>,
     pub arguments: Vec<String>,
     pub expected_exit_status: ExpectedExitStatus,
+    pub timeout: Option<Duration>,
+    pub env: BTreeMap<String, String>,
+    pub env_remove: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub stdin: Option<StdinInput>,
+    pub expected_stdout: Option<OutputAssertions>,
+    pub expected_stderr: Option<OutputAssertions>,
+    pub snapshot: bool,
+    pub filters: Vec<OutputFilter>,
+    pub tags: Vec<String>,
+    /// How many job slots a run takes up in parallel mode
+    pub slots: u32,
+    /// Runs of the same group never overlap in parallel mode
+    pub exclusive_group: Option<String>,
+    /// How often a failed run is repeated before it counts as failed
+    pub retries: u32,
+    /// The reason why the run is expected to fail
+    pub xfail: Option<String>,
+    /// Skips the run if any of these conditions holds
+    pub skip_if: Option<RunConditions>,
+    /// Skips the run unless all of these conditions hold
+    pub only_if: Option<RunConditions>,
+    /// Skips the run unless the target matches this `cfg(..)` expression
+    pub only_on: Option<CfgExpression>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn config_args(value: serde_json::Value) -> ConfigArgs {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn merged(config: serde_json::Value, template: serde_json::Value) -> ConfigArgs {
+        let mut config = config_args(config);
+        config.join_inplace(config_args(template));
+        config
+    }
+
+    /// The options of the configuration win, the template only fills in the missing ones
+    fn assert_overrides(template: serde_json::Value, config: serde_json::Value) {
+        assert_eq!(
+            merged(config.clone(), template.clone()),
+            config_args(config)
+        );
+        assert_eq!(merged(json!({}), template.clone()), config_args(template));
+    }
+
+    #[test]
+    fn timeout_is_overridden() {
+        assert_overrides(json!({ "timeout": "1m" }), json!({ "timeout": "5s" }));
+    }
+
+    #[test]
+    fn env_is_combined() {
+        let template = json!({
+            "env": { "A": "template", "B": "template" },
+            "env_remove": ["X", "Y"],
+        });
+        let config = json!({
+            "env": { "A": "configuration" },
+            "env_remove": ["Y", "Z"],
+        });
+
+        assert_eq!(
+            merged(config, template),
+            config_args(json!({
+                "env": { "A": "configuration", "B": "template" },
+                "env_remove": ["Y", "Z", "X"],
+            }))
+        );
+    }
+
+    #[test]
+    fn template_filters_go_first() {
+        let template = json!({
+            "filters": [{ "regex": "a", "replace": "b" }, { "regex": "c", "replace": "d" }],
+        });
+        let config = json!({
+            "filters": [{ "regex": "e", "replace": "f" }, { "regex": "a", "replace": "b" }],
+        });
+
+        assert_eq!(
+            merged(config, template),
+            config_args(json!({
+                "filters": [
+                    { "regex": "c", "replace": "d" },
+                    { "regex": "e", "replace": "f" },
+                    { "regex": "a", "replace": "b" },
+                ],
+            }))
+        );
+    }
+
+    #[test]
+    fn tags_are_combined() {
+        assert_eq!(
+            merged(
+                json!({ "tags": ["io", "net"] }),
+                json!({ "tags": ["slow", "io"] })
+            ),
+            config_args(json!({ "tags": ["io", "net", "slow"] }))
+        );
+    }
+
+    #[test]
+    fn retries_are_overridden() {
+        assert_overrides(json!({ "retries": 3 }), json!({ "retries": 0 }));
+    }
+
+    #[test]
+    fn xfail_is_overridden() {
+        assert_overrides(
+            json!({ "xfail": "template" }),
+            json!({ "xfail": "configuration" }),
+        );
+    }
+
+    #[test]
+    fn run_conditions_are_overridden() {
+        assert_overrides(
+            json!({ "skip_if": { "env": "CI" }, "only_if": { "command": "git" } }),
+            json!({
+                "skip_if": { "env_unset": "HOME" },
+                "only_if": { "file_exists": "input.txt" },
+            }),
+        );
+    }
+
+    #[test]
+    fn only_on_is_overridden() {
+        assert_overrides(
+            json!({ "only_on": "cfg(unix)" }),
+            json!({ "only_on": "cfg(windows)" }),
+        );
+    }
 }
@@ -1,10 +1,10 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::Display};
 
 use miette::Diagnostic;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 
 use crate::{
-    config_args::{ConfigArgs, FinalizedConfigArgs},
+    config_args::{deserialize_either, ConfigArgs, FinalizedConfigArgs},
     struct_merge::StructMerge,
 };
 
@@ -18,18 +18,33 @@ pub enum RunnerType {
     Ignore,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-#[serde(untagged)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum RunnerOptionVariants {
     Type(RunnerType),
-    Options(RunnerOptions),
+    Options(Box<RunnerOptions>),
+}
+
+impl<'de> Deserialize<'de> for RunnerOptionVariants {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_either(
+            deserializer,
+            serde_json::Value::is_string,
+            |value| RunnerType::deserialize(value).map(Self::Type),
+            |value| {
+                RunnerOptions::deserialize(value).map(|options| Self::Options(Box::new(options)))
+            },
+        )
+    }
 }
 
 impl From<RunnerOptionVariants> for RunnerOptions {
     fn from(value: RunnerOptionVariants) -> Self {
         match value {
             RunnerOptionVariants::Type(t) => RunnerOptions::from_type(t),
-            RunnerOptionVariants::Options(o) => o,
+            RunnerOptionVariants::Options(o) => *o,
         }
     }
 }
@@ -74,6 +89,7 @@ impl RunnerOptions {
         Self {
             config: ExampleConfig {
                 r#type: Some(r#type),
+                template: None,
                 args: Default::default(),
             },
             template: None,
@@ -81,10 +97,7 @@ impl RunnerOptions {
     }
 
     pub fn extract_config(mut self) -> ExampleConfig {
-        if self.config.args.label.is_none() {
-            self.config.args.label = self.template
-        }
-
+        self.config.template = self.template;
         self.config
     }
 }
@@ -92,6 +105,9 @@ impl RunnerOptions {
 #[derive(Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ExampleConfig {
     pub r#type: Option<RunnerType>,
+    /// The template the configuration is based on
+    #[serde(skip)]
+    pub template: Option<String>,
     #[serde(flatten)]
     pub args: ConfigArgs,
 }
@@ -106,10 +122,35 @@ impl StructMerge for ExampleConfig {
 }
 
 impl ExampleConfig {
-    pub fn with_default_type(self, r#type: RunnerType) -> ExampleConfigFinalized {
+    pub fn with_default_type(
+        self,
+        r#type: RunnerType,
+        origin: ConfigurationOrigin,
+    ) -> ExampleConfigFinalized {
         ExampleConfigFinalized {
             r#type: self.r#type.unwrap_or(r#type),
+            template: self.template,
             args: self.args.finalize(),
+            origin,
+        }
+    }
+}
+
+/// Where a configuration was declared, with its index in the declaring list. This gives
+/// configurations an identity that stays the same across runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ConfigurationOrigin {
+    WorkspaceDefault(usize),
+    PackageDefault(usize),
+    Explicit(usize),
+}
+
+impl Display for ConfigurationOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigurationOrigin::WorkspaceDefault(i) => write!(f, "workspace-default-{i}"),
+            ConfigurationOrigin::PackageDefault(i) => write!(f, "package-default-{i}"),
+            ConfigurationOrigin::Explicit(i) => write!(f, "explicit-{i}"),
         }
     }
 }
@@ -117,5 +158,20 @@ impl ExampleConfig {
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ExampleConfigFinalized {
     pub r#type: RunnerType,
+    /// The template the configuration is based on, which labels it unless that is ambiguous
+    pub template: Option<String>,
     pub args: FinalizedConfigArgs,
+    pub origin: ConfigurationOrigin,
+}
+
+impl ExampleConfigFinalized {
+    /// The label of the configuration, falling back to an identifier derived from its origin.
+    /// Labels after templates are filled in by `label_after_templates` once all configurations of
+    /// the example are known.
+    pub fn label(&self) -> String {
+        self.args
+            .label
+            .clone()
+            .unwrap_or_else(|| self.origin.to_string())
+    }
 }
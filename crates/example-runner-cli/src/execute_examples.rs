@@ -1,5 +1,375 @@
-trait RunnableExample {}
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::PathBuf,
+    process::{ExitStatus, Stdio},
+    sync::Arc,
+    time::Duration,
+};
 
-struct ExamplesExecutor {
-    examples: Vec<Box<dyn RunnableExample>>,
+use cargo_metadata::{camino::Utf8PathBuf, Message, Package};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt},
+    sync::watch,
+};
+
+use crate::reporters::{Event, OutputStream, Reporter};
+
+pub type StdioHandles = (
+    tokio::task::JoinHandle<Result<String, tokio::io::Error>>,
+    tokio::task::JoinHandle<Result<String, tokio::io::Error>>,
+);
+
+pub type ConfigurationNames = (String, String, String);
+
+pub enum RunResult {
+    /// The example was run and exited with the given status
+    Exited(ExitStatus),
+    /// The example didn't exit within its timeout and was killed
+    TimedOut(Duration),
+    /// The example is `no_run` and compiled successfully
+    Built,
+    /// The example didn't compile, so it couldn't be run
+    BuildFailed,
+    /// The example compiled without an executable because its crate type isn't `bin`
+    NoExecutable,
+    /// The `stdin` file couldn't be read, so the example wasn't run
+    StdinReadFailed { path: PathBuf, error: String },
+    /// The configuration is of type `ignore`
+    Ignored,
+    /// The conditions of `skip_if` or `only_if` skipped the run, for the given reason
+    Skipped(String),
+    /// The run was skipped or cancelled because too many runs failed
+    NotRun,
+}
+
+/// The output of an example, captured in addition to streaming it to the terminal
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CapturedOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// What an example was run with, recorded for the reports
+#[derive(Debug, Clone)]
+pub struct Invocation {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Variables set (`Some`) or removed (`None`) on top of the environment of the runner
+    pub env: Vec<(String, Option<String>)>,
+    pub cwd: Option<PathBuf>,
+}
+
+impl From<&tokio::process::Command> for Invocation {
+    fn from(command: &tokio::process::Command) -> Self {
+        let command = command.as_std();
+
+        Self {
+            program: command.get_program().to_string_lossy().into_owned(),
+            args: command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            env: command
+                .get_envs()
+                .map(|(key, value)| {
+                    (
+                        key.to_string_lossy().into_owned(),
+                        value.map(|value| value.to_string_lossy().into_owned()),
+                    )
+                })
+                .collect(),
+            cwd: command.get_current_dir().map(PathBuf::from),
+        }
+    }
+}
+
+/// The examples of a package that compiled successfully keyed by their name, with their executable
+/// if they have one. Examples with a crate type other than `bin` compile to a library instead.
+pub type BuiltExamples = HashMap<String, Option<Utf8PathBuf>>;
+
+/// Builds all examples of a package in one cargo invocation
+pub async fn build_package_examples(
+    package: &Package,
+) -> std::io::Result<(BuiltExamples, ExitStatus)> {
+    let mut child = tokio::process::Command::new("cargo")
+        .arg("build")
+        .arg("-p")
+        .arg(&package.name)
+        .arg("--examples")
+        // Keep building the remaining examples if one of them fails to compile
+        .arg("--keep-going")
+        .arg("--message-format=json")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .stdin(Stdio::null())
+        .spawn()?;
+
+    let stdout = child.stdout.take().unwrap();
+    let mut root_stderr = tokio::io::stderr();
+    let mut built = HashMap::new();
+
+    let mut buf_reader = tokio::io::BufReader::new(stdout).lines();
+    while let Some(line) = buf_reader.next_line().await? {
+        match serde_json::from_str::<Message>(&line).unwrap_or(Message::TextLine(line)) {
+            Message::CompilerArtifact(artifact) if artifact.target.is_example() => {
+                built.insert(artifact.target.name, artifact.executable);
+            }
+            Message::CompilerMessage(message) => {
+                if let Some(rendered) = message.message.rendered {
+                    root_stderr.write_all(rendered.as_bytes()).await?;
+                }
+            }
+            Message::TextLine(line) => {
+                root_stderr
+                    .write_all(format!("{line}\n").as_bytes())
+                    .await?;
+            }
+            _ => {}
+        }
+    }
+
+    // Failed examples simply don't produce an artifact, so the exit status is only informational
+    let exit_status = child.wait().await?;
+
+    Ok((built, exit_status))
+}
+
+/// Creates the command to run an example executable. If it may have to be killed, the example
+/// becomes the leader of its own process group, so it can be killed together with everything it
+/// spawned.
+pub fn example_command(executable: &Utf8PathBuf, killable: bool) -> tokio::process::Command {
+    #[allow(unused_mut)]
+    let mut command = std::process::Command::new(executable);
+
+    #[cfg(unix)]
+    if killable {
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    }
+
+    let mut command = tokio::process::Command::from(command);
+    command.kill_on_drop(killable);
+    command
+}
+
+/// The process groups of the examples that are running in their own group, see `example_command`
+#[cfg(unix)]
+static PROCESS_GROUPS: std::sync::Mutex<Vec<libc::pid_t>> = std::sync::Mutex::new(Vec::new());
+
+/// Keeps the process group of a running example registered for `forward_signals`
+struct LiveProcessGroup(#[cfg(unix)] Option<libc::pid_t>);
+
+impl LiveProcessGroup {
+    fn register(child: &tokio::process::Child) -> Self {
+        #[cfg(unix)]
+        {
+            // Only examples that lead their own group miss the signals sent by the terminal
+            // SAFETY: getpgid has no memory safety preconditions
+            let pid = child
+                .id()
+                .map(|pid| pid as libc::pid_t)
+                .filter(|&pid| unsafe { libc::getpgid(pid) } == pid);
+            if let Some(pid) = pid {
+                PROCESS_GROUPS.lock().unwrap().push(pid);
+            }
+            Self(pid)
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = child;
+            Self()
+        }
+    }
+}
+
+impl Drop for LiveProcessGroup {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.0 {
+            PROCESS_GROUPS.lock().unwrap().retain(|&group| group != pid);
+        }
+    }
+}
+
+/// Forwards SIGINT and SIGTERM to the examples running in their own process group, which the
+/// terminal doesn't send its Ctrl-C to, and then exits like the signal would have
+#[cfg(unix)]
+pub fn forward_signals() -> std::io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::spawn(async move {
+        let signal = tokio::select! {
+            _ = interrupt.recv() => libc::SIGINT,
+            _ = terminate.recv() => libc::SIGTERM,
+        };
+
+        // Keep the lock until exiting, so no example registers its group in the meantime
+        let groups = PROCESS_GROUPS.lock().unwrap();
+        for &group in groups.iter() {
+            // SAFETY: killpg has no memory safety preconditions, the group is led by our own child
+            unsafe { libc::killpg(group, signal) };
+        }
+        std::process::exit(128 + signal);
+    });
+
+    Ok(())
+}
+
+/// Waits for the example to exit, killing it and its process group once the timeout is exceeded
+/// or the run gets cancelled.
+pub async fn wait_for_example(
+    child: &mut tokio::process::Child,
+    timeout: Option<Duration>,
+    cancelled: impl Future<Output = ()>,
+) -> std::io::Result<RunResult> {
+    let _group = LiveProcessGroup::register(child);
+    let timed_out = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+
+    let result = tokio::select! {
+        exit_status = child.wait() => return Ok(RunResult::Exited(exit_status?)),
+        _ = timed_out => RunResult::TimedOut(timeout.unwrap_or_default()),
+        _ = cancelled => RunResult::NotRun,
+    };
+
+    kill_process_group(child)?;
+    child.wait().await?;
+    Ok(result)
+}
+
+fn kill_process_group(child: &mut tokio::process::Child) -> std::io::Result<()> {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: killpg has no memory safety preconditions, the pid is the one of our own child
+        // which is the leader of its process group, see `example_command`
+        if unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) } == 0 {
+            return Ok(());
+        }
+    }
+
+    child.start_kill()
+}
+
+/// How long the output of a killed example is still read. Processes it started in a new session
+/// aren't killed with its process group and may keep the pipes open indefinitely.
+const KILLED_OUTPUT_GRACE: Duration = Duration::from_secs(1);
+
+/// The tasks reading the output of an example
+pub struct OutputReaders {
+    handles: StdioHandles,
+    stop: watch::Sender<bool>,
+}
+
+impl OutputReaders {
+    /// Waits until the output was read to the end, or only for `KILLED_OUTPUT_GRACE` if the
+    /// example was killed, returning what was captured
+    pub async fn captured(self, killed: bool) -> std::io::Result<CapturedOutput> {
+        let Self {
+            handles: (stdout, stderr),
+            stop,
+        } = self;
+
+        let stop_reading = async {
+            if killed {
+                tokio::time::sleep(KILLED_OUTPUT_GRACE).await;
+                stop.send_replace(true);
+            }
+            std::future::pending::<()>().await
+        };
+
+        tokio::select! {
+            stdout = stdout => Ok(CapturedOutput {
+                stdout: stdout??,
+                stderr: stderr.await??,
+            }),
+            _ = stop_reading => unreachable!(),
+        }
+    }
+}
+
+/// Spawns the example, streaming its output to the reporter while capturing it. The command can be
+/// spawned again for retries.
+pub fn run_command(
+    names: &ConfigurationNames,
+    command: &mut tokio::process::Command,
+    stdin: Option<&[u8]>,
+    reporter: Arc<dyn Reporter>,
+) -> std::io::Result<(tokio::process::Child, OutputReaders)> {
+    let mut child = command.spawn()?;
+
+    if let Some(input) = stdin {
+        let input = input.to_vec();
+        let mut child_stdin = child.stdin.take().unwrap();
+        tokio::spawn(async move {
+            // The example may exit without reading all of its input, which isn't an error in itself
+            let _ = child_stdin.write_all(&input).await;
+            // Dropping the handle closes the pipe so the example sees EOF
+            drop(child_stdin);
+        });
+    }
+
+    let (stop, stopped) = watch::channel(false);
+    let stdout_handle = read_output(
+        names.clone(),
+        OutputStream::Stdout,
+        child.stdout.take().unwrap(),
+        reporter.clone(),
+        stopped.clone(),
+    );
+    let stderr_handle = read_output(
+        names.clone(),
+        OutputStream::Stderr,
+        child.stderr.take().unwrap(),
+        reporter,
+        stopped,
+    );
+
+    Ok((
+        child,
+        OutputReaders {
+            handles: (stdout_handle, stderr_handle),
+            stop,
+        },
+    ))
+}
+
+/// Streams the lines of an output stream to the reporter and captures them, until the stream
+/// ends or reading is stopped
+fn read_output(
+    names: ConfigurationNames,
+    stream: OutputStream,
+    pipe: impl AsyncRead + Unpin + Send + 'static,
+    reporter: Arc<dyn Reporter>,
+    mut stopped: watch::Receiver<bool>,
+) -> tokio::task::JoinHandle<Result<String, tokio::io::Error>> {
+    tokio::spawn(async move {
+        let mut captured = String::new();
+        let mut buf_reader = tokio::io::BufReader::new(pipe).lines();
+        loop {
+            let line = tokio::select! {
+                line = buf_reader.next_line() => line?,
+                _ = stopped.wait_for(|stopped| *stopped) => None,
+            };
+            let Some(line) = line else {
+                break;
+            };
+
+            reporter.report(Event::OutputLine {
+                names: &names,
+                stream,
+                line: &line,
+            });
+            captured.push_str(&line);
+            captured.push('\n');
+        }
+
+        Ok(captured)
+    })
 }
@@ -0,0 +1,131 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tokio::sync::watch;
+
+use crate::run_report::Verdict;
+
+/// Cancels the remaining runs once `max_fail` runs failed
+pub struct FailureLimit {
+    max_fail: Option<usize>,
+    /// Count flaky runs as failed, like `--flaky-is-failure` does for the exit status
+    flaky_is_failure: bool,
+    failures: AtomicUsize,
+    cancel: watch::Sender<bool>,
+}
+
+impl FailureLimit {
+    pub fn new(max_fail: Option<usize>, flaky_is_failure: bool) -> Self {
+        Self {
+            max_fail,
+            flaky_is_failure,
+            failures: AtomicUsize::new(0),
+            cancel: watch::Sender::new(false),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.max_fail.is_some()
+    }
+
+    pub fn record(&self, verdict: &Verdict) {
+        let is_failed =
+            verdict.is_failed() || (self.flaky_is_failure && matches!(verdict, Verdict::Flaky(_)));
+        if !is_failed {
+            return;
+        }
+
+        let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.max_fail.is_some_and(|max_fail| failures >= max_fail) {
+            self.cancel.send_replace(true);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancel.borrow()
+    }
+
+    /// Completes once the remaining runs are cancelled
+    pub async fn cancelled(&self) {
+        let mut cancelled = self.cancel.subscribe();
+        // The sender lives as long as `self`, so waiting can't fail
+        let _ = cancelled.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::run_report::Failure;
+
+    use super::*;
+
+    fn failed() -> Verdict {
+        Verdict::Failed(vec![Failure::BuildFailed])
+    }
+
+    fn flaky() -> Verdict {
+        Verdict::Flaky(vec![Failure::BuildFailed])
+    }
+
+    #[test]
+    fn without_limit_nothing_is_cancelled() {
+        let limit = FailureLimit::new(None, false);
+        for _ in 0..10 {
+            limit.record(&failed());
+        }
+
+        assert!(!limit.is_active());
+        assert!(!limit.is_cancelled());
+    }
+
+    #[test]
+    fn cancels_once_the_limit_is_reached() {
+        let limit = FailureLimit::new(Some(2), false);
+        limit.record(&failed());
+        limit.record(&Verdict::Passed);
+        limit.record(&Verdict::NotRun);
+        assert!(!limit.is_cancelled());
+
+        limit.record(&failed());
+        assert!(limit.is_cancelled());
+    }
+
+    #[test]
+    fn flaky_runs_only_count_with_flaky_is_failure() {
+        let limit = FailureLimit::new(Some(1), false);
+        limit.record(&flaky());
+        assert!(!limit.is_cancelled());
+
+        let limit = FailureLimit::new(Some(1), true);
+        limit.record(&flaky());
+        assert!(limit.is_cancelled());
+    }
+
+    #[test]
+    fn expected_failures_dont_count() {
+        let limit = FailureLimit::new(Some(1), true);
+        limit.record(&Verdict::ExpectedFailure {
+            reason: "bug".to_owned(),
+            failures: vec![Failure::BuildFailed],
+        });
+
+        assert!(!limit.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn wakes_up_waiting_runs() {
+        let limit = FailureLimit::new(Some(1), false);
+        let cancelled = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::join!(limit.cancelled(), async { limit.record(&failed()) });
+        });
+
+        assert!(cancelled.await.is_ok());
+        // Runs starting after the cancellation don't wait either
+        assert!(
+            tokio::time::timeout(Duration::from_secs(5), limit.cancelled())
+                .await
+                .is_ok()
+        );
+    }
+}
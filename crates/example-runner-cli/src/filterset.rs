@@ -0,0 +1,368 @@
+use globset::{Glob, GlobMatcher};
+use miette::{Diagnostic, SourceSpan};
+use regex::Regex;
+use rust_metadata::span::visible_span;
+
+use crate::execute_examples::ConfigurationNames;
+
+/// A parsed filter expression like `package(core) & !example(bench_*)`
+#[derive(Debug)]
+pub enum Filterset {
+    All,
+    None,
+    Package(NameMatcher),
+    Example(NameMatcher),
+    Label(NameMatcher),
+    /// Matches if any of the tags matches
+    Tag(NameMatcher),
+    Not(Box<Filterset>),
+    And(Box<Filterset>, Box<Filterset>),
+    Or(Box<Filterset>, Box<Filterset>),
+}
+
+/// The argument of a predicate, either a glob or a `/regex/`
+#[derive(Debug)]
+pub enum NameMatcher {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl NameMatcher {
+    fn is_match(&self, name: &str) -> bool {
+        match self {
+            NameMatcher::Glob(glob) => glob.is_match(name),
+            NameMatcher::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Diagnostic)]
+#[diagnostic(
+    code(filterset::parse),
+    help(
+        "Filter expressions combine `package(..)`, `example(..)`, `label(..)`, `tag(..)`, `all()` and `none()` with `&`, `|`, `!` and parentheses. Predicates take a glob or a `/regex/`"
+    )
+)]
+#[error("Invalid filter expression")]
+pub struct FiltersetError {
+    #[source_code]
+    expression: String,
+    #[label("{reason}")]
+    span: SourceSpan,
+    reason: String,
+}
+
+impl Filterset {
+    pub fn parse(expression: &str) -> Result<Self, FiltersetError> {
+        let mut parser = Parser {
+            input: expression,
+            position: 0,
+        };
+
+        let filterset = parser.or()?;
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(filterset),
+            Some(c) => Err(parser.error(
+                parser.position,
+                c.len_utf8(),
+                format!("expected `&`, `|` or the end, found `{c}`"),
+            )),
+        }
+    }
+
+    pub fn matches(&self, names: &ConfigurationNames, tags: &[String]) -> bool {
+        let (package, example, label) = names;
+
+        match self {
+            Filterset::All => true,
+            Filterset::None => false,
+            Filterset::Package(matcher) => matcher.is_match(package),
+            Filterset::Example(matcher) => matcher.is_match(example),
+            Filterset::Label(matcher) => matcher.is_match(label),
+            Filterset::Tag(matcher) => tags.iter().any(|tag| matcher.is_match(tag)),
+            Filterset::Not(filterset) => !filterset.matches(names, tags),
+            Filterset::And(left, right) => left.matches(names, tags) && right.matches(names, tags),
+            Filterset::Or(left, right) => left.matches(names, tags) || right.matches(names, tags),
+        }
+    }
+}
+
+/// A recursive descent parser, with `|` binding weaker than `&` which binds weaker than `!`
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn or(&mut self) -> Result<Filterset, FiltersetError> {
+        let mut filterset = self.and()?;
+        while self.eat('|') {
+            filterset = Filterset::Or(Box::new(filterset), Box::new(self.and()?));
+        }
+
+        Ok(filterset)
+    }
+
+    fn and(&mut self) -> Result<Filterset, FiltersetError> {
+        let mut filterset = self.not()?;
+        while self.eat('&') {
+            filterset = Filterset::And(Box::new(filterset), Box::new(self.not()?));
+        }
+
+        Ok(filterset)
+    }
+
+    fn not(&mut self) -> Result<Filterset, FiltersetError> {
+        if self.eat('!') {
+            Ok(Filterset::Not(Box::new(self.not()?)))
+        } else {
+            self.atom()
+        }
+    }
+
+    fn atom(&mut self) -> Result<Filterset, FiltersetError> {
+        self.skip_whitespace();
+        let start = self.position;
+
+        if self.eat('(') {
+            let filterset = self.or()?;
+            if !self.eat(')') {
+                return Err(self.error(start, 1, "unclosed parenthesis".to_owned()));
+            }
+            return Ok(filterset);
+        }
+
+        let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        if name.is_empty() {
+            let length = self.peek().map(char::len_utf8).unwrap_or(0);
+            return Err(self.error(
+                start,
+                length,
+                "expected a predicate like `package(..)`, `!` or `(`".to_owned(),
+            ));
+        }
+
+        self.skip_whitespace();
+        if !self.eat('(') {
+            return Err(self.error(start, name.len(), format!("expected `(` after `{name}`")));
+        }
+
+        let predicate = match name {
+            "all" | "none" => {
+                if !self.eat(')') {
+                    return Err(self.error(
+                        start,
+                        self.position - start,
+                        format!("`{name}()` takes no argument"),
+                    ));
+                }
+                if name == "all" {
+                    Filterset::All
+                } else {
+                    Filterset::None
+                }
+            }
+            "package" => Filterset::Package(self.matcher()?),
+            "example" => Filterset::Example(self.matcher()?),
+            "label" => Filterset::Label(self.matcher()?),
+            "tag" => Filterset::Tag(self.matcher()?),
+            _ => return Err(self.error(start, name.len(), format!("unknown predicate `{name}`"))),
+        };
+
+        Ok(predicate)
+    }
+
+    /// Parses the argument of a predicate including the closing parenthesis
+    fn matcher(&mut self) -> Result<NameMatcher, FiltersetError> {
+        self.skip_whitespace();
+        let start = self.position;
+
+        let matcher = if self.eat('/') {
+            let mut pattern = String::new();
+            let mut escaped = false;
+            loop {
+                match self.next() {
+                    None => {
+                        return Err(self.error(
+                            start,
+                            self.position - start,
+                            "unclosed regex, expected a closing `/`".to_owned(),
+                        ))
+                    }
+                    Some('/') if !escaped => break,
+                    Some('\\') if !escaped => escaped = true,
+                    Some(c) => {
+                        // `\/` only escapes the delimiter, everything else is up to the regex
+                        if escaped && c != '/' {
+                            pattern.push('\\');
+                        }
+                        pattern.push(c);
+                        escaped = false;
+                    }
+                }
+            }
+
+            NameMatcher::Regex(Regex::new(&pattern).map_err(|error| {
+                self.error(
+                    start,
+                    self.position - start,
+                    format!(
+                        "invalid regex: {}",
+                        error.to_string().lines().last().unwrap_or_default()
+                    ),
+                )
+            })?)
+        } else {
+            let glob = self.take_while(|c| c != ')').trim_end();
+            if glob.is_empty() {
+                return Err(self.error(start, 0, "expected a glob or a `/regex/`".to_owned()));
+            }
+
+            NameMatcher::Glob(
+                Glob::new(glob)
+                    .map_err(|error| {
+                        self.error(start, glob.len(), format!("invalid glob: {}", error.kind()))
+                    })?
+                    .compile_matcher(),
+            )
+        };
+
+        if !self.eat(')') {
+            let length = self.peek().map(char::len_utf8).unwrap_or(0);
+            return Err(self.error(self.position, length, "expected `)`".to_owned()));
+        }
+
+        Ok(matcher)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    /// Skips whitespace and consumes `expected` if it comes next
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.position += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let start = self.position;
+        while self.peek().is_some_and(&predicate) {
+            self.next();
+        }
+
+        &self.input[start..self.position]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn error(&self, offset: usize, length: usize, reason: String) -> FiltersetError {
+        FiltersetError {
+            expression: self.input.to_owned(),
+            span: visible_span(self.input, offset, length),
+            reason,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(package: &str, example: &str, label: &str) -> ConfigurationNames {
+        (package.to_owned(), example.to_owned(), label.to_owned())
+    }
+
+    fn matches(expression: &str, names: &ConfigurationNames, tags: &[&str]) -> bool {
+        let tags: Vec<String> = tags.iter().map(|tag| tag.to_string()).collect();
+        Filterset::parse(expression).unwrap().matches(names, &tags)
+    }
+
+    fn error(expression: &str) -> FiltersetError {
+        Filterset::parse(expression).unwrap_err()
+    }
+
+    #[test]
+    fn predicates() {
+        let hello = names("core", "hello_world", "explicit-0");
+
+        assert!(matches("all()", &hello, &[]));
+        assert!(!matches("none()", &hello, &[]));
+        assert!(matches("package(core)", &hello, &[]));
+        assert!(!matches("package(cli)", &hello, &[]));
+        assert!(matches("example(hello_*)", &hello, &[]));
+        assert!(matches("label(explicit-?)", &hello, &[]));
+        assert!(matches("tag(slow)", &hello, &["fast", "slow"]));
+        assert!(!matches("tag(*)", &hello, &[]));
+    }
+
+    #[test]
+    fn regex_arguments() {
+        let hello = names("core", "hello_world", "a/b");
+
+        assert!(matches("example(/^hello_\\w+$/)", &hello, &[]));
+        assert!(!matches("example(/^world/)", &hello, &[]));
+        // `\/` escapes the delimiter
+        assert!(matches("label(/^a\\/b$/)", &hello, &[]));
+    }
+
+    #[test]
+    fn precedence() {
+        let hello = names("core", "hello", "default");
+
+        // `&` binds stronger than `|`
+        assert!(matches("none() & none() | all()", &hello, &[]));
+        assert!(!matches("none() & (none() | all())", &hello, &[]));
+        // `!` binds stronger than `&`
+        assert!(matches("!none() & all()", &hello, &[]));
+        assert!(!matches("!(none() | all())", &hello, &[]));
+        assert!(matches("!!package(core)", &hello, &[]));
+    }
+
+    #[test]
+    fn whitespace() {
+        let hello = names("core", "hello", "default");
+
+        assert!(matches("  package( core )&!example (bye)  ", &hello, &[]));
+    }
+
+    #[test]
+    fn errors() {
+        for (expression, reason, offset) in [
+            ("", "expected a predicate like `package(..)`, `!` or `(`", 0),
+            ("packages(core)", "unknown predicate `packages`", 0),
+            ("package", "expected `(` after `package`", 0),
+            ("package()", "expected a glob or a `/regex/`", 8),
+            ("package(core", "expected `)`", 11),
+            ("all(core)", "`all()` takes no argument", 0),
+            ("(all()", "unclosed parenthesis", 0),
+            ("all() all()", "expected `&`, `|` or the end, found `a`", 6),
+            (
+                "example(/hello)",
+                "unclosed regex, expected a closing `/`",
+                8,
+            ),
+        ] {
+            let error = error(expression);
+            assert_eq!(error.reason, reason, "{expression}");
+            assert_eq!(error.span.offset(), offset, "{expression}");
+        }
+
+        assert!(error("example(/(/)").reason.starts_with("invalid regex: "));
+        assert!(error("example([)").reason.starts_with("invalid glob: "));
+    }
+}
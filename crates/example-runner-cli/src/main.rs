@@ -2,25 +2,55 @@ mod cargo_metadata_async;
 mod config_args;
 mod example_config;
 mod execute_examples;
+mod fail_fast;
+mod filterset;
+mod output_assertions;
+mod output_filters;
 mod read_example_configuration;
+mod reporters;
+mod run_conditions;
+mod run_report;
+mod selection;
+mod snapshots;
 mod struct_merge;
 mod templates;
 
-use std::process::Stdio;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use cargo_metadata::MetadataCommand;
+use cargo_metadata::{Metadata, MetadataCommand, Package};
 use clap::Parser;
-use futures::{
-    stream::{FuturesOrdered, FuturesUnordered},
-    StreamExt,
-};
+use futures::{stream::FuturesOrdered, StreamExt};
 use miette::Diagnostic;
 use read_example_configuration::ExamplesConfigurationError;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::{
+    io::AsyncWriteExt,
+    sync::{Mutex, Semaphore},
+};
 
 use crate::{
-    cargo_metadata_async::AsyncExecMetadata, example_config::ExampleConfigFinalized,
+    cargo_metadata_async::AsyncExecMetadata,
+    config_args::StdinInput,
+    example_config::{ExampleConfigFinalized, RunnerType},
+    execute_examples::{
+        build_package_examples, example_command, run_command, wait_for_example, CapturedOutput,
+        ConfigurationNames, Invocation, RunResult,
+    },
+    fail_fast::FailureLimit,
+    filterset::FiltersetError,
     read_example_configuration::ExamplesConfiguration,
+    reporters::{
+        html_report, junit_report, reporter, step_summary, Event, MessageFormat, Reporter,
+    },
+    run_conditions::{skip_reason, target_cfg, TargetCfgError},
+    run_report::{Evaluator, RunReport, Verdict},
+    selection::SelectionArgs,
+    snapshots::snapshot_path,
 };
 
 #[derive(Parser, Debug)]
@@ -35,6 +65,45 @@ struct Args {
     /// Run examples in parallel?
     #[arg(short, long, default_value = "false")]
     parallel: bool,
+    /// How many job slots parallel runs may take up at once, implies `--parallel` [default: the
+    /// available parallelism]
+    #[arg(short, long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    jobs: Option<u32>,
+    /// Stop after the first failed run, cancelling running examples and skipping the remaining ones
+    #[arg(long, conflicts_with = "max_fail")]
+    fail_fast: bool,
+    /// Stop after this many failed runs, cancelling running examples and skipping the remaining
+    /// ones
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_fail: Option<u64>,
+    /// Repeat failed runs up to this many times, overriding any configured retries
+    #[arg(long, value_name = "N")]
+    retries: Option<u32>,
+    /// Fail if a run only passed after being retried
+    #[arg(long)]
+    flaky_is_failure: bool,
+    /// Write the output of `snapshot` configurations to their snapshot files instead of comparing
+    #[arg(long)]
+    bless: bool,
+    /// Kill examples running longer than this (e.g. `30s`), overriding any configured timeout
+    #[arg(long, value_parser = humantime::parse_duration)]
+    timeout: Option<Duration>,
+    /// How to print progress and results [default: github if `GITHUB_ACTIONS` is set, human
+    /// otherwise]
+    #[arg(long, visible_alias = "format", value_enum)]
+    message_format: Option<MessageFormat>,
+    /// Write a JUnit XML report to this path
+    #[arg(long, value_name = "PATH")]
+    junit: Option<PathBuf>,
+    /// Write a self-contained HTML report to this path
+    #[arg(long, value_name = "PATH")]
+    html_report: Option<PathBuf>,
+    /// Evaluate `only_on` against this target triple instead of the host. The examples are still
+    /// built and run for the host
+    #[arg(long, value_name = "TRIPLE")]
+    cfg_target: Option<String>,
+    #[command(flatten)]
+    selection: SelectionArgs,
 }
 
 #[derive(thiserror::Error, Debug, Diagnostic)]
@@ -48,6 +117,9 @@ enum AppError {
     #[diagnostic(transparent)]
     #[error(transparent)]
     ExampleConfigurationsRead(#[from] ExamplesConfigurationError),
+    #[diagnostic(transparent)]
+    #[error(transparent)]
+    Filterset(#[from] FiltersetError),
     #[diagnostic(
         code(app::unconfigured_example),
         help("Check the output above for unconfigured examples and configure them")
@@ -62,6 +134,16 @@ enum AppError {
     )]
     #[error("An unconfigured example was encountered")]
     UnknownExample,
+    #[diagnostic(transparent)]
+    #[error(transparent)]
+    TargetCfg(#[from] TargetCfgError),
+    #[diagnostic(code(app::report_write))]
+    #[error("Failed to write the report `{}`", path.display())]
+    ReportWrite {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
     #[error(transparent)]
     ExampleRun(#[from] std::io::Error),
     #[error(transparent)]
@@ -72,6 +154,24 @@ enum AppError {
     )]
     #[error("An unsucessful example run was encountered")]
     ExampleUnsuccessful,
+    #[diagnostic(
+        code(app::flaky_example),
+        help("Check the output above for flaky examples and fix them")
+    )]
+    #[error("A flaky example run was encountered")]
+    FlakyExample,
+    #[diagnostic(
+        code(app::snapshot_collision),
+        help(
+            "Give one of the configurations a label that differs in more than special characters"
+        )
+    )]
+    #[error("The configurations `{first}` and `{second}` share the snapshot file `{}`", path.display())]
+    SnapshotCollision {
+        first: String,
+        second: String,
+        path: PathBuf,
+    },
 }
 
 #[tokio::main]
@@ -81,6 +181,16 @@ async fn main() -> miette::Result<()> {
 
 async fn main_wrapper() -> Result<(), AppError> {
     let args = Args::parse();
+    #[cfg(unix)]
+    execute_examples::forward_signals()?;
+    let reporter = reporter(args.message_format.unwrap_or_else(|| {
+        if std::env::var_os("GITHUB_ACTIONS").is_some() {
+            MessageFormat::Github
+        } else {
+            MessageFormat::Human
+        }
+    }));
+    let selection = args.selection.into_selection()?;
 
     let metadata = MetadataCommand::new().exec_async().await?;
 
@@ -88,33 +198,32 @@ async fn main_wrapper() -> Result<(), AppError> {
         examples,
         unconfigured,
         unknown,
+        serial,
     } = ExamplesConfiguration::from_metadata(&metadata).await?;
 
     let unconfigured_examples_present = !unconfigured.is_empty();
     for (package_id, target) in unconfigured {
-        println!(
-            "Unconfigured example found in crate {}: {}",
-            metadata
+        reporter.report(Event::UnconfiguredExample {
+            package: &metadata
                 .packages
                 .iter()
                 .find(|package| package.id == package_id)
                 .unwrap()
                 .name,
-            target.name
-        )
+            example: &target.name,
+        });
     }
     let unknown_examples_present = !unknown.is_empty();
     for (package_id, example_name) in unknown {
-        println!(
-            "Unknown example configured in crate {}: {}",
-            metadata
+        reporter.report(Event::UnknownExample {
+            package: &metadata
                 .packages
                 .iter()
                 .find(|package| package.id == package_id)
                 .unwrap()
                 .name,
-            example_name
-        )
+            example: &example_name,
+        });
     }
 
     if args.error_on_unconfigured && unconfigured_examples_present {
@@ -125,170 +234,417 @@ async fn main_wrapper() -> Result<(), AppError> {
         return Err(AppError::UnknownExample);
     }
 
-    let to_be_run = examples.iter().flat_map(|(id, configurations)| {
-        let package = metadata
-            .packages
-            .iter()
-            .find(|package| package.id == id.0)
-            .unwrap();
-        configurations.iter().enumerate().map(|(i, configuration)| {
-            let mut command = tokio::process::Command::new("cargo");
-            command
-                .arg("run")
-                .arg("-p")
-                .arg(&package.name)
-                .arg("--example")
-                .arg(&id.1.name)
-                .arg("--")
-                .args(configuration.args.arguments.clone())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .stdin(Stdio::null());
-            (
+    // Selecting only after resolving keeps the checks above covering the whole workspace
+    let (runs, deselected): (Vec<_>, Vec<_>) = examples
+        .iter()
+        .flat_map(|(id, configurations)| {
+            let package = metadata
+                .packages
+                .iter()
+                .find(|package| package.id == id.0)
+                .unwrap();
+            configurations.iter().map(move |configuration| {
                 (
-                    package.name.clone(),
-                    id.1.name.clone(),
-                    configuration
-                        .args
-                        .label
-                        .as_ref()
-                        .map(ToOwned::to_owned)
-                        .unwrap_or_else(|| i.to_string()),
-                ),
-                command,
-                configuration.to_owned(),
-            )
+                    (
+                        package.name.clone(),
+                        id.1.name.clone(),
+                        configuration.label(),
+                    ),
+                    package,
+                    configuration.to_owned(),
+                )
+            })
         })
+        .partition(|(names, _, configuration)| selection.matches(names, &configuration.args.tags));
+
+    // Labels are sanitized for file names, so different labels can end up with the same snapshot
+    let mut snapshot_owners = HashMap::new();
+    for (names, package, _) in runs
+        .iter()
+        .chain(&deselected)
+        .filter(|(_, _, configuration)| configuration.args.snapshot)
+    {
+        let manifest_dir = package
+            .manifest_path
+            .parent()
+            .unwrap_or(&package.manifest_path);
+        let path = snapshot_path(manifest_dir.as_std_path(), names);
+        let (package_name, example, label) = names;
+        let name = format!("{package_name}::examples::{example}::{label}");
+        if let Some(first) = snapshot_owners.insert(path.clone(), name.clone()) {
+            return Err(AppError::SnapshotCollision {
+                first,
+                second: name,
+                path,
+            });
+        }
+    }
+
+    reporter.report(Event::Planned {
+        runs: runs.len(),
+        filtered_out: deselected.len(),
     });
 
-    let results = if args.parallel {
-        let mut handles = to_be_run.map(run_command).collect::<Result<Vec<_>, _>>()?;
+    let mut built_examples = HashMap::new();
+    for (_, package, _) in runs
+        .iter()
+        .filter(|(_, _, configuration)| configuration.r#type != RunnerType::Ignore)
+    {
+        if !built_examples.contains_key(&package.id) {
+            reporter.report(Event::BuildStarted {
+                package: &package.name,
+            });
+            let start = Instant::now();
+            let (package_examples, exit_status) = build_package_examples(package).await?;
+            reporter.report(Event::BuildFinished {
+                package: &package.name,
+                success: exit_status.success(),
+                duration: start.elapsed(),
+            });
+            built_examples.insert(package.id.clone(), package_examples);
+        }
+    }
 
-        let exit_codes = handles
-            .iter_mut()
-            .map(|(child, _, _, _)| async move { child.wait().await })
-            .collect::<FuturesOrdered<_>>()
-            .collect::<Vec<_>>()
+    // Only ask rustc for the cfg options if any configuration is limited to some platforms
+    let target_cfg = if runs
+        .iter()
+        .any(|(_, _, configuration)| configuration.args.only_on.is_some())
+    {
+        target_cfg(args.cfg_target.as_deref()).await?
+    } else {
+        Vec::new()
+    };
+
+    let evaluator = Evaluator::new(&metadata, args.bless);
+    let failure_limit = FailureLimit::new(
+        if args.fail_fast {
+            Some(1)
+        } else {
+            args.max_fail.map(|max_fail| max_fail as usize)
+        },
+        args.flaky_is_failure,
+    );
+
+    let mut to_be_run = Vec::new();
+    for (names, package, mut configuration) in runs {
+        if args.timeout.is_some() {
+            configuration.args.timeout = args.timeout;
+        }
+        if let Some(retries) = args.retries {
+            configuration.args.retries = retries;
+        }
+
+        let built = built_examples
+            .get(&package.id)
+            .and_then(|package_examples| package_examples.get(&names.1));
+
+        let manifest_dir = package
+            .manifest_path
+            .parent()
+            .unwrap_or(&package.manifest_path);
+        let skip_reason = skip_reason(&configuration.args, manifest_dir.as_std_path(), &target_cfg);
+
+        let preparation = match (&configuration.r#type, built, skip_reason) {
+            (RunnerType::Ignore, _, _) => Preparation::Finished(RunResult::Ignored),
+            (_, _, Some(reason)) => Preparation::Finished(RunResult::Skipped(reason)),
+            // Cargo leaves out examples whose required features aren't enabled without a word
+            (_, None, None) => {
+                Preparation::Finished(match missing_features(&metadata, package, &names.1) {
+                    Some(features) => RunResult::Skipped(features),
+                    None => RunResult::BuildFailed,
+                })
+            }
+            (RunnerType::NoRun, Some(_), None) => Preparation::Finished(RunResult::Built),
+            (RunnerType::Explicit, Some(None), None) => {
+                Preparation::Finished(RunResult::NoExecutable)
+            }
+            // A missing input only fails this configuration, the others still run
+            (RunnerType::Explicit, Some(Some(executable)), None) => match read_stdin(
+                configuration.args.stdin.as_ref(),
+                manifest_dir.as_std_path(),
+            )
             .await
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>()?;
+            {
+                Err(result) => Preparation::Finished(result),
+                Ok(stdin) => {
+                    let mut command = example_command(
+                        executable,
+                        configuration.args.timeout.is_some() || failure_limit.is_active(),
+                    );
+                    command
+                        .args(configuration.args.arguments.clone())
+                        // `cargo run` sets this at runtime too, so keep examples relying on it working
+                        .env("CARGO_MANIFEST_DIR", manifest_dir);
+                    for key in &configuration.args.env_remove {
+                        command.env_remove(key);
+                    }
+                    command.envs(&configuration.args.env);
+                    if let Some(cwd) = &configuration.args.cwd {
+                        command.current_dir(manifest_dir.as_std_path().join(cwd));
+                    }
+                    command.stdout(Stdio::piped()).stderr(Stdio::piped()).stdin(
+                        if stdin.is_some() {
+                            Stdio::piped()
+                        } else {
+                            Stdio::null()
+                        },
+                    );
+                    Preparation::Execute { command, stdin }
+                }
+            },
+        };
+
+        to_be_run.push((names, configuration, preparation));
+    }
+
+    let reports = if (args.parallel || args.jobs.is_some()) && !serial {
+        let jobs = args.jobs.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|parallelism| u32::try_from(parallelism.get()).unwrap_or(u32::MAX))
+                .unwrap_or(1)
+        });
+        // The semaphore is fair, so heavy runs waiting for slots aren't starved by lighter ones
+        let job_slots = Semaphore::new(jobs as usize);
+        let exclusive_groups = to_be_run
+            .iter()
+            .filter_map(|(_, configuration, _)| configuration.args.exclusive_group.clone())
+            .map(|group| (group, Mutex::new(())))
+            .collect::<HashMap<_, _>>();
 
-        exit_codes
+        to_be_run
             .into_iter()
-            .zip(handles.into_iter())
-            .map(|(exit_code, (_, handles, config, names))| async move {
-                handles.0.await??;
-                handles.1.await??;
-                Ok::<_, AppError>((exit_code, config, names))
+            .map(|prepared_run| {
+                let job_slots = &job_slots;
+                let exclusive_groups = &exclusive_groups;
+                let evaluator = &evaluator;
+                let failure_limit = &failure_limit;
+                let reporter = reporter.clone();
+                async move {
+                    let (_, configuration, preparation) = &prepared_run;
+                    let is_executed = matches!(preparation, Preparation::Execute { .. });
+                    // Waiting for the group first keeps runs from holding job slots while idle
+                    let _group_guard = match &configuration.args.exclusive_group {
+                        Some(group) if is_executed => Some(exclusive_groups[group].lock().await),
+                        _ => None,
+                    };
+                    // Runs weighing more than all slots would wait forever, so cap their weight.
+                    // Runs that aren't executed take a slot too, so they come up in declaration
+                    // order like the others.
+                    // The semaphore is never closed, so acquiring can't fail
+                    let slots = if is_executed {
+                        configuration.args.slots.clamp(1, jobs)
+                    } else {
+                        1
+                    };
+                    let _permit = job_slots.acquire_many(slots).await.unwrap();
+                    execute_run(prepared_run, evaluator, failure_limit, reporter).await
+                }
             })
-            .collect::<FuturesUnordered<_>>()
+            .collect::<FuturesOrdered<_>>()
             .collect::<Vec<_>>()
             .await
             .into_iter()
             .collect::<Result<Vec<_>, _>>()?
     } else {
-        let mut results = Vec::new();
+        let mut reports = Vec::new();
 
-        for command in to_be_run {
-            let (mut child, handles, config, names) = run_command(command)?;
-            results.push((child.wait().await?, config, names));
-            handles.0.await??;
-            handles.1.await??;
+        for prepared_run in to_be_run {
+            reports.push(
+                execute_run(prepared_run, &evaluator, &failure_limit, reporter.clone()).await?,
+            );
         }
 
-        results
+        reports
     };
 
-    let mut unsucessful = false;
-    for (exit_status, config, (package, example, config_label)) in results {
-        if !config.args.expected_exit_status.matches(&exit_status) {
-            unsucessful = true;
-            println!("Example run [{package} {example} <{config_label}>] executed unsucessfully with {exit_status}!", );
-        } else {
-            println!("Example run [{package} {example} <{config_label}>] suceeded!");
-        }
+    reporter.report(Event::Finished { reports: &reports });
+
+    if let Some(path) = args.junit {
+        tokio::fs::write(&path, junit_report(&reports))
+            .await
+            .map_err(|source| AppError::ReportWrite { path, source })?;
     }
 
-    if unsucessful {
+    if let Some(path) = args.html_report {
+        tokio::fs::write(&path, html_report(&reports))
+            .await
+            .map_err(|source| AppError::ReportWrite { path, source })?;
+    }
+
+    if let Some(path) = std::env::var_os("GITHUB_STEP_SUMMARY").map(PathBuf::from) {
+        append_to_file(&path, &step_summary(&reports))
+            .await
+            .map_err(|source| AppError::ReportWrite { path, source })?;
+    }
+
+    if reports.iter().any(|report| report.verdict.is_failed()) {
         Err(AppError::ExampleUnsuccessful)
+    } else if args.flaky_is_failure
+        && reports
+            .iter()
+            .any(|report| matches!(report.verdict, Verdict::Flaky(_)))
+    {
+        Err(AppError::FlakyExample)
     } else {
         Ok(())
     }
 }
 
-type StdioHandles = (
-    tokio::task::JoinHandle<Result<(), tokio::io::Error>>,
-    tokio::task::JoinHandle<Result<(), tokio::io::Error>>,
-);
-
-type ConfigurationNames = (String, String, String);
-
-fn run_command(
-    ((package_name, target_name, config_name), mut command, configuration): (
-        ConfigurationNames,
-        tokio::process::Command,
-        ExampleConfigFinalized,
-    ),
-) -> std::io::Result<(
-    tokio::process::Child,
-    StdioHandles,
-    ExampleConfigFinalized,
-    ConfigurationNames,
-)> {
-    let mut child = command.spawn()?;
-
-    let stdout = child.stdout.take().unwrap();
-    let mut root_stdout = tokio::io::stdout();
-    let package_name_copy = package_name.clone();
-    let target_name_copy = target_name.clone();
-    let config_name_copy = config_name.clone();
-    let stdout_handle = tokio::spawn(async move {
-        let mut buf_reader = tokio::io::BufReader::new(stdout).lines();
-        while let Some(line) = buf_reader.next_line().await? {
-            root_stdout
-                .write_all(
-                    format!(
-                        "[{package} {example} <{config}>] {line}\n",
-                        package = package_name_copy,
-                        example = target_name_copy,
-                        config = config_name_copy
-                    )
-                    .as_bytes(),
-                )
-                .await?;
+/// Describes the `required-features` of an example that aren't enabled, if there are any
+fn missing_features(metadata: &Metadata, package: &Package, example: &str) -> Option<String> {
+    let target = package
+        .targets
+        .iter()
+        .find(|target| target.is_example() && target.name == example)?;
+    let enabled = metadata
+        .resolve
+        .as_ref()
+        .and_then(|resolve| resolve.nodes.iter().find(|node| node.id == package.id))
+        .map(|node| node.features.as_slice())
+        .unwrap_or_default();
+    let missing = target
+        .required_features
+        .iter()
+        .filter(|feature| !enabled.contains(feature))
+        .map(|feature| format!("`{feature}`"))
+        .collect::<Vec<_>>();
+
+    (!missing.is_empty()).then(|| format!("needs the features {}", missing.join(", ")))
+}
+
+/// Reads the input of an example, failing only its run if the input file can't be read
+async fn read_stdin(
+    stdin: Option<&StdinInput>,
+    manifest_dir: &Path,
+) -> Result<Option<Vec<u8>>, RunResult> {
+    match stdin {
+        Some(StdinInput::Inline(input)) => Ok(Some(input.clone().into_bytes())),
+        Some(StdinInput::File { file }) => {
+            let path = manifest_dir.join(file);
+            tokio::fs::read(&path)
+                .await
+                .map(Some)
+                .map_err(|error| RunResult::StdinReadFailed {
+                    path,
+                    error: error.to_string(),
+                })
         }
+        None => Ok(None),
+    }
+}
 
-        Ok::<_, std::io::Error>(())
-    });
+async fn append_to_file(path: &Path, text: &str) -> std::io::Result<()> {
+    tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?
+        .write_all(text.as_bytes())
+        .await
+}
+
+/// What happens with a configuration once its turn comes
+enum Preparation {
+    /// Run the example with the command, writing the input to its stdin
+    Execute {
+        command: tokio::process::Command,
+        stdin: Option<Vec<u8>>,
+    },
+    /// The example isn't run, for example because it is ignored or failed to build
+    Finished(RunResult),
+}
 
-    let stderr = child.stderr.take().unwrap();
-    let mut root_stderr = tokio::io::stderr();
-    let package_name_copy = package_name.clone();
-    let target_name_copy = target_name.clone();
-    let config_name_copy = config_name.clone();
-    let stderr_handle = tokio::spawn(async move {
-        let mut buf_reader = tokio::io::BufReader::new(stderr).lines();
-        while let Some(line) = buf_reader.next_line().await? {
-            root_stderr
-                .write_all(
-                    format!(
-                        "[{package} {example} <{config}>] {line}\n",
-                        package = package_name_copy,
-                        example = target_name_copy,
-                        config = config_name_copy
-                    )
-                    .as_bytes(),
+type PreparedRun = (ConfigurationNames, ExampleConfigFinalized, Preparation);
+
+/// Runs a configuration, or reports why it isn't run. Either way it happens in declaration order,
+/// so failures count toward `--max-fail` only once their turn comes.
+async fn execute_run(
+    (names, configuration, preparation): PreparedRun,
+    evaluator: &Evaluator<'_>,
+    failure_limit: &FailureLimit,
+    reporter: Arc<dyn Reporter>,
+) -> Result<RunReport, AppError> {
+    reporter.report(Event::RunStarted { names: &names });
+
+    let (mut command, stdin) = match preparation {
+        Preparation::Execute { command, stdin } if !failure_limit.is_cancelled() => {
+            (command, stdin)
+        }
+        preparation => {
+            // Once cancelled, only the configurations that weren't going to run anyway keep their
+            // result, everything else is skipped like the runs
+            let result = match preparation {
+                Preparation::Finished(
+                    result @ (RunResult::Ignored | RunResult::Skipped(_) | RunResult::Built),
+                ) => result,
+                Preparation::Finished(result) if !failure_limit.is_cancelled() => result,
+                _ => RunResult::NotRun,
+            };
+            let report = evaluator
+                .evaluate(
+                    names,
+                    configuration,
+                    result,
+                    None,
+                    CapturedOutput::default(),
+                    Duration::ZERO,
                 )
-                .await?;
+                .await;
+            reporter.report(Event::RunFinished { report: &report });
+            failure_limit.record(&report.verdict);
+            return Ok(report);
         }
+    };
 
-        Ok::<_, std::io::Error>(())
-    });
+    let invocation = Invocation::from(&command);
+    let mut previous: Option<RunReport> = None;
+    let mut attempts = 0;
+    let report = loop {
+        attempts += 1;
+        let start = Instant::now();
+        let (mut child, readers) =
+            run_command(&names, &mut command, stdin.as_deref(), reporter.clone())?;
+        let result = wait_for_example(
+            &mut child,
+            configuration.args.timeout,
+            failure_limit.cancelled(),
+        )
+        .await?;
+        let duration = start.elapsed();
+        let output = readers
+            .captured(!matches!(result, RunResult::Exited(_)))
+            .await?;
+
+        let mut report = evaluator
+            .evaluate(
+                names.clone(),
+                configuration.clone(),
+                result,
+                Some(invocation.clone()),
+                output,
+                duration,
+            )
+            .await;
+        report.attempts = attempts;
+
+        if matches!(report.result, RunResult::NotRun) {
+            // Cancelling a retry doesn't undo the failure of the attempt before
+            break previous.unwrap_or(report);
+        }
+
+        if report.verdict.is_retried(&configuration.args, attempts) && !failure_limit.is_cancelled()
+        {
+            previous = Some(report);
+            continue;
+        }
+
+        if let Some(previous) = previous {
+            report.verdict = report.verdict.after_retry(previous.verdict);
+        }
+        break report;
+    };
+    reporter.report(Event::RunFinished { report: &report });
+    failure_limit.record(&report.verdict);
 
-    Ok((
-        child,
-        (stdout_handle, stderr_handle),
-        configuration,
-        (package_name, target_name, config_name),
-    ))
+    Ok(report)
 }
@@ -0,0 +1,158 @@
+use serde::{Deserialize, Deserializer};
+use similar::TextDiff;
+
+use crate::config_args::{multi_line_regexes, ConfigRegex};
+
+/// Assertions on the captured output of one stream of an example
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OutputAssertions {
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub contains: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub not_contains: Vec<String>,
+    #[serde(default, deserialize_with = "multi_line_regexes")]
+    pub regex: Vec<ConfigRegex>,
+    pub equals: Option<String>,
+}
+
+pub fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(one) => vec![one],
+        OneOrMany::Many(many) => many,
+    })
+}
+
+impl OutputAssertions {
+    /// Checks the output against all assertions, returning a readable report of every failed one
+    /// together with a diff or the captured output
+    pub fn check(&self, output: &str) -> Option<String> {
+        let mut failures = Vec::new();
+
+        for expected in &self.contains {
+            if !output.contains(expected.as_str()) {
+                failures.push(format!("expected output to contain `{expected}`"));
+            }
+        }
+
+        for unexpected in &self.not_contains {
+            if output.contains(unexpected.as_str()) {
+                failures.push(format!("expected output not to contain `{unexpected}`"));
+            }
+        }
+
+        for regex in &self.regex {
+            if !regex.0.is_match(output) {
+                failures.push(format!("expected output to match `{}`", regex.0.as_str()));
+            }
+        }
+
+        let diff = self
+            .equals
+            .as_ref()
+            .filter(|expected| *expected != output)
+            .map(|expected| {
+                failures.push("expected output to equal the given text".to_owned());
+                unified_diff(expected, output)
+            });
+
+        if failures.is_empty() {
+            return None;
+        }
+
+        let mut report = failures
+            .into_iter()
+            .map(|failure| format!("  - {failure}\n"))
+            .collect::<String>();
+        match diff {
+            Some(diff) => report.push_str(&diff),
+            None => {
+                report.push_str("captured output:\n");
+                report.extend(output.lines().map(|line| format!("    {line}\n")));
+            }
+        }
+
+        Some(report)
+    }
+}
+
+/// A unified diff from the expected to the actual text
+pub fn unified_diff(expected: &str, actual: &str) -> String {
+    TextDiff::from_lines(expected, actual)
+        .unified_diff()
+        .header("expected", "actual")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assertions(json: serde_json::Value) -> OutputAssertions {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn passes_if_every_assertion_holds() {
+        let assertions = assertions(serde_json::json!({
+            "contains": ["hello", "world"],
+            "not_contains": "goodbye",
+            "regex": "^world$",
+            "equals": "hello\nworld\n",
+        }));
+
+        assert_eq!(assertions.check("hello\nworld\n"), None);
+    }
+
+    #[test]
+    fn regexes_match_per_line() {
+        let assertions = assertions(serde_json::json!({ "regex": ["^second$", "^first"] }));
+
+        assert_eq!(assertions.check("first\nsecond\n"), None);
+        assert_eq!(
+            assertions.check("first second\n"),
+            Some(
+                "  - expected output to match `^second$`\ncaptured output:\n    first second\n"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn reports_every_failed_assertion_with_the_output() {
+        let assertions = assertions(serde_json::json!({
+            "contains": "hello",
+            "not_contains": "goodbye",
+        }));
+
+        assert_eq!(
+            assertions.check("goodbye\n"),
+            Some(
+                "  - expected output to contain `hello`\n  - expected output not to contain `goodbye`\ncaptured output:\n    goodbye\n"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn equals_reports_a_diff() {
+        let assertions = assertions(serde_json::json!({ "equals": "hello\nworld\n" }));
+
+        assert_eq!(
+            assertions.check("hello\nthere\n"),
+            Some(
+                "  - expected output to equal the given text\n--- expected\n+++ actual\n@@ -1,2 +1,2 @@\n hello\n-world\n+there\n"
+                    .to_owned()
+            )
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::borrow::Cow;
+
+use cargo_metadata::Metadata;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    config_args::{deserialize_either, ConfigRegex},
+    execute_examples::CapturedOutput,
+};
+
+/// Normalizes captured output before it is compared by assertions or snapshots
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OutputFilter {
+    Preset(FilterPreset),
+    Replace { regex: ConfigRegex, replace: String },
+}
+
+impl<'de> Deserialize<'de> for OutputFilter {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Replace {
+            regex: ConfigRegex,
+            replace: String,
+        }
+
+        deserialize_either(
+            deserializer,
+            serde_json::Value::is_string,
+            |value| FilterPreset::deserialize(value).map(Self::Preset),
+            |value| {
+                Replace::deserialize(value)
+                    .map(|Replace { regex, replace }| Self::Replace { regex, replace })
+            },
+        )
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FilterPreset {
+    /// Replaces the target directory with `[TARGET_DIR]` and the workspace root with `[WORKSPACE]`
+    #[serde(rename = "paths")]
+    Paths,
+    /// Strips ANSI escape codes
+    #[serde(rename = "ansi")]
+    Ansi,
+}
+
+/// Matches CSI sequences (colors, cursor movement, ...) as well as OSC sequences (hyperlinks, ...)
+const ANSI_ESCAPE_REGEX: &str = r"\x1b\[[0-9:;<=>?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)";
+
+pub struct OutputFilters<'a> {
+    workspace_root: &'a str,
+    target_directory: &'a str,
+    ansi_escape: Regex,
+}
+
+impl<'a> OutputFilters<'a> {
+    pub fn new(metadata: &'a Metadata) -> Self {
+        Self {
+            workspace_root: metadata.workspace_root.as_str(),
+            target_directory: metadata.target_directory.as_str(),
+            ansi_escape: Regex::new(ANSI_ESCAPE_REGEX).unwrap(),
+        }
+    }
+
+    /// Applies the filters in order to both streams of the output
+    pub fn apply(&self, filters: &[OutputFilter], output: &CapturedOutput) -> CapturedOutput {
+        let mut stdout = Cow::Borrowed(output.stdout.as_str());
+        let mut stderr = Cow::Borrowed(output.stderr.as_str());
+
+        for filter in filters {
+            match filter {
+                OutputFilter::Preset(FilterPreset::Paths) => {
+                    // The target directory usually lives inside of the workspace, so replace it first
+                    for (path, replacement) in [
+                        (self.target_directory, "[TARGET_DIR]"),
+                        (self.workspace_root, "[WORKSPACE]"),
+                    ] {
+                        stdout = Cow::Owned(stdout.replace(path, replacement));
+                        stderr = Cow::Owned(stderr.replace(path, replacement));
+                    }
+                }
+                OutputFilter::Preset(FilterPreset::Ansi) => {
+                    stdout = Cow::Owned(self.ansi_escape.replace_all(&stdout, "").into_owned());
+                    stderr = Cow::Owned(self.ansi_escape.replace_all(&stderr, "").into_owned());
+                }
+                OutputFilter::Replace {
+                    regex: ConfigRegex(regex),
+                    replace,
+                } => {
+                    stdout = Cow::Owned(regex.replace_all(&stdout, replace.as_str()).into_owned());
+                    stderr = Cow::Owned(regex.replace_all(&stderr, replace.as_str()).into_owned());
+                }
+            }
+        }
+
+        CapturedOutput {
+            stdout: stdout.into_owned(),
+            stderr: stderr.into_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> Metadata {
+        serde_json::from_value(serde_json::json!({
+            "packages": [],
+            "workspace_members": [],
+            "resolve": null,
+            "workspace_root": "/work",
+            "target_directory": "/work/target",
+            "version": 1,
+        }))
+        .unwrap()
+    }
+
+    fn filters(json: serde_json::Value) -> Vec<OutputFilter> {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn output(stdout: &str, stderr: &str) -> CapturedOutput {
+        CapturedOutput {
+            stdout: stdout.to_owned(),
+            stderr: stderr.to_owned(),
+        }
+    }
+
+    #[test]
+    fn paths_replace_the_target_directory_first() {
+        let metadata = metadata();
+        let filtered = OutputFilters::new(&metadata).apply(
+            &filters(serde_json::json!(["paths"])),
+            &output("/work/target/debug/demo", "/work/src/main.rs"),
+        );
+
+        assert_eq!(
+            filtered,
+            output("[TARGET_DIR]/debug/demo", "[WORKSPACE]/src/main.rs")
+        );
+    }
+
+    #[test]
+    fn replacements_apply_in_order() {
+        let metadata = metadata();
+        let filtered = OutputFilters::new(&metadata).apply(
+            &filters(serde_json::json!([
+                { "regex": r"\d+ms", "replace": "[DURATION]" },
+                { "regex": r"\[(\w+)\]", "replace": "<$1>" },
+            ])),
+            &output("took 12ms", "took 3ms"),
+        );
+
+        assert_eq!(filtered, output("took <DURATION>", "took <DURATION>"));
+    }
+
+    #[test]
+    fn invalid_regexes_keep_their_error() {
+        let error = serde_json::from_value::<OutputFilter>(
+            serde_json::json!({ "regex": "(", "replace": "" }),
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().starts_with("invalid regex `(`"));
+    }
+
+    #[test]
+    fn ansi_strips_escape_sequences() {
+        let ansi_escape = Regex::new(ANSI_ESCAPE_REGEX).unwrap();
+
+        for (escaped, stripped) in [
+            ("\x1b[1;31merror\x1b[0m", "error"),
+            ("\x1b[38:5:208morange\x1b[m", "orange"),
+            ("\x1b[2K\x1b[1Gprogress", "progress"),
+            ("\x1b]8;;https://example.com\x07link\x1b]8;;\x07", "link"),
+            (
+                "\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\",
+                "link",
+            ),
+        ] {
+            assert_eq!(ansi_escape.replace_all(escaped, ""), stripped);
+        }
+    }
+}
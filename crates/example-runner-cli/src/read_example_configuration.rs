@@ -1,21 +1,26 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashSet};
 
 use cargo_metadata::{Metadata, PackageId, Target};
 use miette::Diagnostic;
 use serde::{Deserialize, Deserializer};
 
 use crate::{
+    config_args::deserialize_either,
     example_config::{
-        ExampleConfigFinalized, RunnerOptionVariants, RunnerOptions, RunnerType,
-        TemplateResolveError,
+        ConfigurationOrigin, ExampleConfigFinalized, RunnerOptionVariants, RunnerOptions,
+        RunnerType, TemplateResolveError,
     },
     templates::{PackageExampleRunnerConfig, WorkspaceExampleRunnerConfig},
 };
 
+/// All examples of the workspace with their configurations. Everything is kept in declaration
+/// order, so runs and their output are the same every time.
 pub struct ExamplesConfiguration<'a> {
-    pub examples: HashMap<(PackageId, &'a Target), HashSet<ExampleConfigFinalized>>,
-    pub unconfigured: HashSet<(PackageId, &'a Target)>,
-    pub unknown: HashSet<(PackageId, String)>,
+    pub examples: Vec<((PackageId, &'a Target), Vec<ExampleConfigFinalized>)>,
+    pub unconfigured: Vec<(PackageId, &'a Target)>,
+    pub unknown: Vec<(PackageId, String)>,
+    /// Whether the workspace forbids running examples in parallel
+    pub serial: bool,
 }
 
 #[derive(Deserialize, Debug, Default)]
@@ -30,39 +35,58 @@ struct PackageMetadata {
     package: PackageConfig,
 }
 
-fn configurations_deserialize<'de, D>(deserializer: D) -> Result<HashSet<RunnerOptions>, D::Error>
+fn configurations_deserialize<'de, D>(deserializer: D) -> Result<Vec<RunnerOptions>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let map_1 = HashSet::<RunnerOptionVariants>::deserialize(deserializer)?;
+    let map_1 = Vec::<RunnerOptionVariants>::deserialize(deserializer)?;
 
     Ok(map_1.into_iter().map(Into::into).collect())
 }
 
 fn examples_deserialize<'de, D>(
     deserializer: D,
-) -> Result<HashMap<String, ExtendedExampleConfig>, D::Error>
+) -> Result<BTreeMap<String, ExtendedExampleConfig>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let map_1 = HashMap::<String, ExampleConfigVariants>::deserialize(deserializer)?;
+    let map_1 = BTreeMap::<String, serde_json::Value>::deserialize(deserializer)?;
 
-    Ok(map_1.into_iter().map(|(k, v)| (k, v.into())).collect())
+    map_1
+        .into_iter()
+        .map(|(k, v)| {
+            ExampleConfigVariants::deserialize(v)
+                .map(|v| (k.clone(), v.into()))
+                .map_err(|error| serde::de::Error::custom(format!("example `{k}`: {error}")))
+        })
+        .collect()
 }
 #[derive(Deserialize, Debug, Default)]
 struct PackageConfig {
     #[serde(flatten, default)]
     example_runner: PackageExampleRunnerConfig,
     #[serde(deserialize_with = "examples_deserialize")]
-    examples: HashMap<String, ExtendedExampleConfig>,
+    examples: BTreeMap<String, ExtendedExampleConfig>,
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(untagged)]
+#[derive(Debug)]
 enum ExampleConfigVariants {
     Extended(ExtendedExampleConfig),
-    #[serde(deserialize_with = "configurations_deserialize")]
-    Simple(HashSet<RunnerOptions>),
+    Simple(Vec<RunnerOptions>),
+}
+
+impl<'de> Deserialize<'de> for ExampleConfigVariants {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_either(
+            deserializer,
+            serde_json::Value::is_array,
+            |value| configurations_deserialize(value).map(Self::Simple),
+            |value| ExtendedExampleConfig::deserialize(value).map(Self::Extended),
+        )
+    }
 }
 
 impl From<ExampleConfigVariants> for ExtendedExampleConfig {
@@ -81,7 +105,7 @@ impl From<ExampleConfigVariants> for ExtendedExampleConfig {
 struct ExtendedExampleConfig {
     extend_configurations: bool,
     #[serde(deserialize_with = "configurations_deserialize")]
-    configurations: HashSet<RunnerOptions>,
+    configurations: Vec<RunnerOptions>,
 }
 
 #[derive(thiserror::Error, Debug, Diagnostic)]
@@ -92,16 +116,83 @@ pub enum ExamplesConfigurationError {
         help("Check your configuration files for syntax errors")
     )]
     ConfigParse(#[from] serde_json::Error),
+    #[diagnostic(
+        code(examples_config::package_config_parse),
+        help("Check the `package.metadata.example_runner` of the package")
+    )]
+    #[error("Invalid example runner configuration of package `{package}`")]
+    PackageConfigParse {
+        package: String,
+        #[source]
+        source: serde_json::Error,
+    },
     #[diagnostic(transparent)]
     #[error(transparent)]
     TemplateResolveError(#[from] TemplateResolveError),
+    #[diagnostic(
+        code(examples_config::duplicate_label),
+        help("Give the configurations of the example distinct labels")
+    )]
+    #[error("The example `{example}` of package `{package}` has more than one configuration labelled `{label}`")]
+    DuplicateLabel {
+        package: String,
+        example: String,
+        label: String,
+    },
+}
+
+/// Appends configurations in order, skipping ones identical to an already present one
+fn extend_unique(
+    configurations: &mut Vec<ExampleConfigFinalized>,
+    new_configurations: impl IntoIterator<Item = ExampleConfigFinalized>,
+) {
+    for new_configuration in new_configurations {
+        if !configurations.iter().any(|configuration| {
+            configuration.r#type == new_configuration.r#type
+                && configuration.template == new_configuration.template
+                && configuration.args == new_configuration.args
+        }) {
+            configurations.push(new_configuration);
+        }
+    }
+}
+
+/// Labels configurations without a label after their template, unless another configuration of
+/// the example has the same label or template. These keep the identifier derived from their
+/// origin, so they can still be told apart.
+fn label_after_templates(configurations: &mut [ExampleConfigFinalized]) {
+    let labels = configurations
+        .iter()
+        .map(|configuration| {
+            let template = configuration
+                .template
+                .as_ref()
+                .filter(|_| configuration.args.label.is_none())?;
+            let is_ambiguous = configurations
+                .iter()
+                .filter(|other| match &other.args.label {
+                    Some(label) => label == template,
+                    None => other.template.as_ref() == Some(template),
+                })
+                .count()
+                > 1;
+
+            (!is_ambiguous).then(|| template.clone())
+        })
+        .collect::<Vec<_>>();
+
+    for (configuration, label) in configurations.iter_mut().zip(labels) {
+        if label.is_some() {
+            configuration.args.label = label;
+        }
+    }
 }
 
 impl<'a> ExamplesConfiguration<'a> {
     pub async fn from_metadata(metadata: &'a Metadata) -> Result<Self, ExamplesConfigurationError> {
-        let mut examples = HashMap::new();
-        let mut unconfigured_examples = HashSet::new();
-        let mut unknown_examples: HashSet<(PackageId, String)> = HashSet::new();
+        let mut examples = Vec::new();
+        let mut unconfigured_examples = Vec::new();
+        let mut unknown_examples = Vec::new();
 
         let mut workspace_config = serde_json::from_value::<Option<WorkspaceMetadata>>(
             metadata.workspace_metadata.clone(),
@@ -116,23 +207,32 @@ impl<'a> ExamplesConfiguration<'a> {
                 default_config.resolve_templates(&workspace_config.templates)?;
                 Ok::<_, TemplateResolveError>(default_config)
             })
-            .collect::<Result<HashSet<_>, _>>()?;
+            .collect::<Result<Vec<_>, _>>()?;
 
-        let workspace_default_configs = workspace_config
-            .default
-            .into_iter()
-            .map(|config| {
-                config
-                    .extract_config()
-                    .with_default_type(RunnerType::Explicit)
-            })
-            .collect::<HashSet<_>>();
+        let mut workspace_default_configs = Vec::new();
+        extend_unique(
+            &mut workspace_default_configs,
+            workspace_config
+                .default
+                .into_iter()
+                .enumerate()
+                .map(|(i, config)| {
+                    config.extract_config().with_default_type(
+                        RunnerType::Explicit,
+                        ConfigurationOrigin::WorkspaceDefault(i),
+                    )
+                }),
+        );
 
         for package in metadata.workspace_packages() {
             let PackageConfig {
                 mut example_runner,
                 examples: mut examples_configs,
-            } = serde_json::from_value::<Option<PackageMetadata>>(package.metadata.clone())?
+            } = serde_json::from_value::<Option<PackageMetadata>>(package.metadata.clone())
+                .map_err(|source| ExamplesConfigurationError::PackageConfigParse {
+                    package: package.name.clone(),
+                    source,
+                })?
                 .unwrap_or_default()
                 .package;
 
@@ -147,71 +247,92 @@ impl<'a> ExamplesConfiguration<'a> {
                         })?;
                     Ok::<_, TemplateResolveError>(default_config)
                 })
-                .collect::<Result<HashSet<_>, _>>()?;
-
-            let package_default_configs = example_runner.default.into_iter().map(|config| {
-                config
-                    .extract_config()
-                    .with_default_type(RunnerType::Explicit)
-            });
-
-            let fallback_configs =
-                if package_default_configs.len() > 0 && !example_runner.extend_workspace_defaults {
-                    package_default_configs.collect::<HashSet<_>>()
-                } else if package_default_configs.len() > 0 {
-                    workspace_default_configs
-                        .iter()
-                        .cloned()
-                        .chain(package_default_configs)
-                        .collect::<HashSet<_>>()
-                } else {
-                    workspace_default_configs.clone()
-                };
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let package_default_configs =
+                example_runner
+                    .default
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, config)| {
+                        config.extract_config().with_default_type(
+                            RunnerType::Explicit,
+                            ConfigurationOrigin::PackageDefault(i),
+                        )
+                    });
+
+            let mut fallback_configs = Vec::new();
+            if package_default_configs.len() == 0 || example_runner.extend_workspace_defaults {
+                extend_unique(
+                    &mut fallback_configs,
+                    workspace_default_configs.iter().cloned(),
+                );
+            }
+            extend_unique(&mut fallback_configs, package_default_configs);
 
             for target in package.targets.iter().filter(|target| target.is_example()) {
                 let explicit_configs = examples_configs.remove(&target.name);
 
                 if explicit_configs.is_none() && fallback_configs.is_empty() {
-                    unconfigured_examples.insert((package.id.clone(), target));
+                    unconfigured_examples.push((package.id.clone(), target));
                     continue;
                 }
 
-                let example_configurations = explicit_configs
+                let mut example_configurations = explicit_configs
                     .map(|configs| -> Result<_, _> {
                         let explicit_configs = configs
                             .configurations
                             .into_iter()
-                            .map(|mut config| {
+                            .enumerate()
+                            .map(|(i, mut config)| {
                                 config
                                     .resolve_templates(&example_runner.templates)
                                     .or_else(|_| {
                                         config.resolve_templates(&workspace_config.templates)
                                     })?;
                                 Ok::<_, TemplateResolveError>(
-                                    config
-                                        .extract_config()
-                                        .with_default_type(RunnerType::Explicit),
+                                    config.extract_config().with_default_type(
+                                        RunnerType::Explicit,
+                                        ConfigurationOrigin::Explicit(i),
+                                    ),
                                 )
                             })
-                            .collect::<Result<HashSet<_>, _>>()?;
-
-                        Ok::<_, TemplateResolveError>(if configs.extend_configurations {
-                            explicit_configs
-                                .into_iter()
-                                .chain(fallback_configs.iter().cloned())
-                                .collect()
-                        } else {
-                            explicit_configs
-                        })
+                            .collect::<Result<Vec<_>, _>>()?;
+
+                        let mut example_configurations = Vec::new();
+                        if configs.extend_configurations {
+                            extend_unique(
+                                &mut example_configurations,
+                                fallback_configs.iter().cloned(),
+                            );
+                        }
+                        extend_unique(&mut example_configurations, explicit_configs);
+
+                        Ok::<_, TemplateResolveError>(example_configurations)
                     })
                     .transpose()?
                     .unwrap_or_else(|| fallback_configs.clone());
 
-                examples.insert((package.id.clone(), target), example_configurations);
+                // The label identifies a configuration in the output, the reports and filters
+                let mut labels = HashSet::new();
+                if let Some(label) = example_configurations
+                    .iter()
+                    .filter_map(|configuration| configuration.args.label.as_ref())
+                    .find(|label| !labels.insert(*label))
+                {
+                    return Err(ExamplesConfigurationError::DuplicateLabel {
+                        package: package.name.clone(),
+                        example: target.name.clone(),
+                        label: label.clone(),
+                    });
+                }
+                label_after_templates(&mut example_configurations);
+
+                examples.push(((package.id.clone(), target), example_configurations));
             }
 
             for config in examples_configs.into_keys() {
-                unknown_examples.insert((package.id.clone(), config));
+                unknown_examples.push((package.id.clone(), config));
             }
         }
 
@@ -219,6 +340,64 @@ impl<'a> ExamplesConfiguration<'a> {
             examples,
             unconfigured: unconfigured_examples,
             unknown: unknown_examples,
+            serial: workspace_config.serial,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config_args::FinalizedConfigArgs;
+
+    use super::*;
+
+    fn configuration(
+        i: usize,
+        template: Option<&str>,
+        label: Option<&str>,
+    ) -> ExampleConfigFinalized {
+        ExampleConfigFinalized {
+            r#type: RunnerType::Explicit,
+            template: template.map(str::to_owned),
+            args: FinalizedConfigArgs {
+                label: label.map(str::to_owned),
+                ..Default::default()
+            },
+            origin: ConfigurationOrigin::Explicit(i),
+        }
+    }
+
+    fn labels(mut configurations: Vec<ExampleConfigFinalized>) -> Vec<String> {
+        label_after_templates(&mut configurations);
+        configurations
+            .iter()
+            .map(ExampleConfigFinalized::label)
+            .collect()
+    }
+
+    #[test]
+    fn labels_after_unique_templates() {
+        assert_eq!(
+            labels(vec![
+                configuration(0, Some("fast"), None),
+                configuration(1, Some("slow"), None),
+                configuration(2, Some("slow"), Some("own")),
+                configuration(3, None, None),
+            ]),
+            ["fast", "slow", "own", "explicit-3"]
+        );
+    }
+
+    #[test]
+    fn ambiguous_templates_keep_the_origin() {
+        assert_eq!(
+            labels(vec![
+                configuration(0, Some("fast"), None),
+                configuration(1, Some("fast"), None),
+                configuration(2, Some("slow"), None),
+                configuration(3, None, Some("slow")),
+            ]),
+            ["explicit-0", "explicit-1", "explicit-2", "slow"]
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::{fmt::Write as _, io::Write, path::PathBuf};
+
+use crate::run_report::{RunReport, Verdict};
+
+use super::{human::HumanReporter, Event, Reporter};
+
+/// The human output, plus workflow commands annotating the sources of failed and flaky examples
+pub struct GithubReporter {
+    /// Annotations need paths relative to the repository root
+    repository_root: Option<PathBuf>,
+}
+
+impl GithubReporter {
+    pub fn new() -> Self {
+        Self {
+            repository_root: std::env::var_os("GITHUB_WORKSPACE").map(PathBuf::from),
+        }
+    }
+
+    fn annotation(&self, report: &RunReport) -> Option<String> {
+        let (command, outcome, failures) = match &report.verdict {
+            Verdict::Failed(failures) => ("error", "failed", failures),
+            Verdict::UnexpectedPass { failures, .. } => ("error", "unexpectedly passed", failures),
+            Verdict::Flaky(failures) => ("warning", "is flaky", failures),
+            _ => return None,
+        };
+        let (package, example, label) = &report.names;
+
+        let file = self
+            .repository_root
+            .as_ref()
+            .and_then(|root| report.source_path.strip_prefix(root).ok())
+            .unwrap_or(&report.source_path);
+        let message = failures
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Some(format!(
+            "::{command} file={},title={}::{}\n",
+            escape_property(&file.to_string_lossy()),
+            escape_property(&format!(
+                "Example run [{package} {example} <{label}>] {outcome}"
+            )),
+            escape_data(&message)
+        ))
+    }
+}
+
+impl Reporter for GithubReporter {
+    fn report(&self, event: Event<'_>) {
+        if let Event::RunFinished { report } = &event {
+            if let Some(annotation) = self.annotation(report) {
+                let _ = std::io::stdout().lock().write_all(annotation.as_bytes());
+            }
+        }
+
+        HumanReporter.report(event);
+    }
+}
+
+/// Renders a Markdown table of all runs for `$GITHUB_STEP_SUMMARY`
+pub fn step_summary(reports: &[RunReport]) -> String {
+    let mut markdown = String::from(
+        "### Example runs\n\n| Package | Example | Configuration | Tags | Result | Duration |\n| --- | --- | --- | --- | --- | --- |\n",
+    );
+
+    for report in reports {
+        let (package, example, label) = &report.names;
+        let result = match &report.verdict {
+            Verdict::Passed => "✅ passed".to_owned(),
+            Verdict::Ignored => "⏭️ ignored".to_owned(),
+            Verdict::NotRun => "⏹️ not run".to_owned(),
+            Verdict::Skipped(reason) => format!("⏭️ skipped ({reason})"),
+            Verdict::ExpectedFailure { reason, .. } => format!("☑️ expected failure ({reason})"),
+            Verdict::UnexpectedPass { reason, .. } => format!("❌ unexpectedly passed ({reason})"),
+            Verdict::Flaky(_) => format!("⚠️ flaky, passed after {} attempts", report.attempts),
+            Verdict::Failed(failures) => format!(
+                "❌ {}",
+                failures
+                    .iter()
+                    // Only the headline of a failure fits into a table cell
+                    .map(|failure| failure
+                        .to_string()
+                        .lines()
+                        .next()
+                        .unwrap_or_default()
+                        .to_owned())
+                    .collect::<Vec<_>>()
+                    .join("<br>")
+            ),
+        };
+
+        writeln!(
+            markdown,
+            "| {} | {} | {} | {} | {} | {:.2}s |",
+            escape_cell(package),
+            escape_cell(example),
+            escape_cell(label),
+            escape_cell(&report.tags.join(", ")),
+            escape_cell(&result),
+            report.duration.as_secs_f64()
+        )
+        .unwrap();
+    }
+
+    markdown
+}
+
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_data_keeps_messages_on_one_line() {
+        assert_eq!(escape_data("100%\r\ndone, a: b"), "100%25%0D%0Adone, a: b");
+    }
+
+    #[test]
+    fn escape_property_escapes_separators() {
+        assert_eq!(
+            escape_property("title: 50%, done\n"),
+            "title%3A 50%25%2C done%0A"
+        );
+    }
+}
@@ -0,0 +1,474 @@
+use std::fmt::Write;
+
+use crate::{
+    execute_examples::{Invocation, RunResult},
+    run_report::{RunReport, Verdict},
+};
+
+use super::{count, escape, seconds};
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2em; color: #1f2328; }
+summary { cursor: pointer; padding: 0.2em 0; }
+details details { margin-left: 1.5em; }
+.passed > summary .status { color: #1a7f37; }
+.failed > summary .status, .unexpectedly-passed > summary .status { color: #cf222e; }
+.flaky > summary .status { color: #9a6700; }
+.ignored > summary .status, .skipped > summary .status, .expected-failure > summary .status, .not-run > summary .status { color: #6e7781; }
+.duration { color: #6e7781; font-size: 0.9em; }
+dl { display: grid; grid-template-columns: max-content auto; gap: 0.3em 1em; margin-left: 1.5em; }
+dt { font-weight: bold; }
+dd { margin: 0; }
+pre { background: #161b22; color: #e6edf3; padding: 0.8em; overflow-x: auto; white-space: pre-wrap; }
+pre.failure { background: #ffebe9; color: #1f2328; }
+";
+
+/// The 16 colors of the ANSI palette, normal colors first and bright ones after
+const ANSI_PALETTE: [&str; 16] = [
+    "#000000", "#cd3131", "#0dbc79", "#e5e510", "#2472c8", "#bc3fbc", "#11a8cd", "#e5e5e5",
+    "#666666", "#f14c4c", "#23d18b", "#f5f543", "#3b8eea", "#d670d6", "#29b8db", "#ffffff",
+];
+
+type ExampleReports<'a> = (&'a str, Vec<&'a RunReport>);
+
+/// Renders a self-contained HTML report with a collapsible tree of packages, examples and their
+/// configurations. Branches containing failures start expanded.
+pub fn html_report(reports: &[RunReport]) -> String {
+    let mut packages: Vec<(&str, Vec<ExampleReports>)> = Vec::new();
+    for report in reports {
+        let (package, example, _) = &report.names;
+        let examples = match packages.iter_mut().find(|(name, _)| name == package) {
+            Some((_, examples)) => examples,
+            None => {
+                packages.push((package, Vec::new()));
+                &mut packages.last_mut().unwrap().1
+            }
+        };
+        match examples.iter_mut().find(|(name, _)| name == example) {
+            Some((_, example_reports)) => example_reports.push(report),
+            None => examples.push((example, vec![report])),
+        }
+    }
+
+    let mut html = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Example runs</title>\n");
+    writeln!(
+        html,
+        "<style>{STYLE}</style>\n</head>\n<body>\n<h1>Example runs</h1>"
+    )
+    .unwrap();
+    writeln!(
+        html,
+        "<p>{} passed, {} failed, {} flaky, {} expected failures, {} ignored, {} skipped, {} not run</p>",
+        count(reports.iter(), |verdict| matches!(verdict, Verdict::Passed)),
+        count(reports.iter(), Verdict::is_failed),
+        count(reports.iter(), |verdict| matches!(
+            verdict,
+            Verdict::Flaky(_)
+        )),
+        count(reports.iter(), |verdict| matches!(
+            verdict,
+            Verdict::ExpectedFailure { .. }
+        )),
+        count(reports.iter(), |verdict| matches!(
+            verdict,
+            Verdict::Ignored
+        )),
+        count(reports.iter(), |verdict| matches!(
+            verdict,
+            Verdict::Skipped(_)
+        )),
+        count(reports.iter(), |verdict| matches!(verdict, Verdict::NotRun)),
+    )
+    .unwrap();
+
+    for (package, examples) in packages {
+        let package_reports = examples
+            .iter()
+            .flat_map(|(_, example_reports)| example_reports.iter().copied())
+            .collect::<Vec<_>>();
+        open_branch(&mut html, package, &package_reports);
+
+        for (example, example_reports) in examples {
+            open_branch(&mut html, example, &example_reports);
+            for report in example_reports {
+                write_configuration(&mut html, report);
+            }
+            html.push_str("</details>\n");
+        }
+
+        html.push_str("</details>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn open_branch(html: &mut String, name: &str, reports: &[&RunReport]) {
+    let status = if reports.iter().any(|report| report.verdict.is_failed()) {
+        "failed"
+    } else if reports
+        .iter()
+        .any(|report| matches!(report.verdict, Verdict::Flaky(_)))
+    {
+        "flaky"
+    } else if reports.iter().all(|report| {
+        matches!(
+            report.verdict,
+            Verdict::Ignored | Verdict::Skipped(_) | Verdict::NotRun
+        )
+    }) {
+        "ignored"
+    } else {
+        "passed"
+    };
+
+    writeln!(
+        html,
+        "<details class=\"{status}\"{}><summary><span class=\"status\">{}</span> {} <span class=\"duration\">{}s</span></summary>",
+        if status == "failed" { " open" } else { "" },
+        status_icon(status),
+        escape(name),
+        seconds(reports.iter().map(|report| report.duration).sum()),
+    )
+    .unwrap();
+}
+
+fn write_configuration(html: &mut String, report: &RunReport) {
+    let status = report.verdict.as_str();
+
+    writeln!(
+        html,
+        "<details class=\"{status}\"{}><summary><span class=\"status\">{} {status}</span> {} <span class=\"duration\">{}s</span></summary>",
+        if report.verdict.is_failed() { " open" } else { "" },
+        status_icon(status),
+        escape(&report.names.2),
+        seconds(report.duration),
+    )
+    .unwrap();
+
+    html.push_str("<dl>\n");
+    let result = match &report.result {
+        RunResult::Exited(exit_status) => exit_status.to_string(),
+        RunResult::TimedOut(timeout) => {
+            format!("timed out after {}", humantime::format_duration(*timeout))
+        }
+        RunResult::Built => "built without running".to_owned(),
+        RunResult::BuildFailed => "failed to build".to_owned(),
+        RunResult::NoExecutable => "built without an executable".to_owned(),
+        RunResult::StdinReadFailed { path, .. } => {
+            format!("failed to read the stdin file {}", path.display())
+        }
+        RunResult::Ignored => "ignored".to_owned(),
+        RunResult::Skipped(reason) => format!("skipped because {reason}"),
+        RunResult::NotRun => "not run because of too many failures".to_owned(),
+    };
+    writeln!(html, "<dt>Result</dt><dd>{}</dd>", escape(&result)).unwrap();
+    if let Verdict::ExpectedFailure { reason, .. } | Verdict::UnexpectedPass { reason, .. } =
+        &report.verdict
+    {
+        writeln!(html, "<dt>Expected to fail</dt><dd>{}</dd>", escape(reason)).unwrap();
+    }
+    if report.attempts > 1 {
+        writeln!(html, "<dt>Attempts</dt><dd>{}</dd>", report.attempts).unwrap();
+    }
+    if !report.tags.is_empty() {
+        writeln!(
+            html,
+            "<dt>Tags</dt><dd>{}</dd>",
+            escape(&report.tags.join(", "))
+        )
+        .unwrap();
+    }
+    writeln!(
+        html,
+        "<dt>Source</dt><dd><code>{}</code></dd>",
+        escape(&report.source_path.to_string_lossy())
+    )
+    .unwrap();
+    if let Some(invocation) = &report.invocation {
+        write_invocation(html, invocation);
+    }
+    html.push_str("</dl>\n");
+
+    if let Verdict::Failed(failures)
+    | Verdict::Flaky(failures)
+    | Verdict::ExpectedFailure { failures, .. }
+    | Verdict::UnexpectedPass { failures, .. } = &report.verdict
+    {
+        for failure in failures {
+            writeln!(
+                html,
+                "<pre class=\"failure\">{}</pre>",
+                escape(&failure.to_string())
+            )
+            .unwrap();
+        }
+    }
+
+    for (stream, output) in [
+        ("stdout", &report.output.stdout),
+        ("stderr", &report.output.stderr),
+    ] {
+        if !output.is_empty() {
+            writeln!(
+                html,
+                "<h4>{stream}</h4>\n<pre>{}</pre>",
+                ansi_to_html(output)
+            )
+            .unwrap();
+        }
+    }
+
+    html.push_str("</details>\n");
+}
+
+fn write_invocation(html: &mut String, invocation: &Invocation) {
+    let command_line = std::iter::once(&invocation.program)
+        .chain(&invocation.args)
+        .map(|arg| shell_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    writeln!(
+        html,
+        "<dt>Command</dt><dd><code>{}</code></dd>",
+        escape(&command_line)
+    )
+    .unwrap();
+
+    if let Some(cwd) = &invocation.cwd {
+        writeln!(
+            html,
+            "<dt>Working directory</dt><dd><code>{}</code></dd>",
+            escape(&cwd.to_string_lossy())
+        )
+        .unwrap();
+    }
+
+    if !invocation.env.is_empty() {
+        html.push_str("<dt>Environment</dt><dd>");
+        for (key, value) in &invocation.env {
+            match value {
+                Some(value) => write!(html, "<code>{}={}</code><br>", escape(key), escape(value)),
+                None => write!(html, "<code>{}</code> (removed)<br>", escape(key)),
+            }
+            .unwrap();
+        }
+        html.push_str("</dd>\n");
+    }
+}
+
+/// Converts the SGR escape sequences for colors and text styles into styled spans, dropping all
+/// other escape sequences
+fn ansi_to_html(text: &str) -> String {
+    let mut html = String::new();
+    let mut style = SgrStyle::default();
+    let mut span_open = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '\x1b' if chars.peek() == Some(&'[') => {
+                chars.next();
+                let mut parameters = String::new();
+                let mut command = None;
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        command = Some(c);
+                        break;
+                    }
+                    parameters.push(c);
+                }
+                if command != Some('m') {
+                    continue;
+                }
+
+                style.apply(&parameters);
+                if span_open {
+                    html.push_str("</span>");
+                }
+                let css = style.css();
+                span_open = !css.is_empty();
+                if span_open {
+                    write!(html, "<span style=\"{css}\">").unwrap();
+                }
+            }
+            '\x1b' => {}
+            c => html.push(c),
+        }
+    }
+
+    if span_open {
+        html.push_str("</span>");
+    }
+    html
+}
+
+#[derive(Default)]
+struct SgrStyle {
+    foreground: Option<String>,
+    background: Option<String>,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+}
+
+impl SgrStyle {
+    fn apply(&mut self, parameters: &str) {
+        // An empty parameter list is a reset, just like `0`
+        let codes = parameters
+            .split(';')
+            .map(|code| code.parse::<u8>().unwrap_or(0))
+            .collect::<Vec<_>>();
+        let mut codes = codes.iter().copied();
+
+        while let Some(code) = codes.next() {
+            match code {
+                0 => *self = Self::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                22 => (self.bold, self.dim) = (false, false),
+                23 => self.italic = false,
+                24 => self.underline = false,
+                30..=37 => self.foreground = Some(ANSI_PALETTE[(code - 30) as usize].to_owned()),
+                90..=97 => {
+                    self.foreground = Some(ANSI_PALETTE[(code - 90 + 8) as usize].to_owned())
+                }
+                40..=47 => self.background = Some(ANSI_PALETTE[(code - 40) as usize].to_owned()),
+                100..=107 => {
+                    self.background = Some(ANSI_PALETTE[(code - 100 + 8) as usize].to_owned())
+                }
+                38 => self.foreground = extended_color(&mut codes),
+                48 => self.background = extended_color(&mut codes),
+                39 => self.foreground = None,
+                49 => self.background = None,
+                _ => {}
+            }
+        }
+    }
+
+    fn css(&self) -> String {
+        let mut css = String::new();
+        if let Some(color) = &self.foreground {
+            write!(css, "color:{color};").unwrap();
+        }
+        if let Some(color) = &self.background {
+            write!(css, "background:{color};").unwrap();
+        }
+        if self.bold {
+            css.push_str("font-weight:bold;");
+        }
+        if self.dim {
+            css.push_str("opacity:0.7;");
+        }
+        if self.italic {
+            css.push_str("font-style:italic;");
+        }
+        if self.underline {
+            css.push_str("text-decoration:underline;");
+        }
+        css
+    }
+}
+
+/// Parses the rest of a `38;5;n` (256 colors) or `38;2;r;g;b` (true color) sequence
+fn extended_color(codes: &mut impl Iterator<Item = u8>) -> Option<String> {
+    match codes.next()? {
+        5 => Some(match codes.next()? {
+            index @ 0..=15 => ANSI_PALETTE[index as usize].to_owned(),
+            index @ 16..=231 => {
+                let index = index - 16;
+                let level = |value: u8| if value == 0 { 0 } else { value * 40 + 55 };
+                format!(
+                    "#{:02x}{:02x}{:02x}",
+                    level(index / 36),
+                    level(index / 6 % 6),
+                    level(index % 6)
+                )
+            }
+            index => {
+                let gray = (index - 232) * 10 + 8;
+                format!("#{gray:02x}{gray:02x}{gray:02x}")
+            }
+        }),
+        2 => Some(format!(
+            "#{:02x}{:02x}{:02x}",
+            codes.next()?,
+            codes.next()?,
+            codes.next()?
+        )),
+        _ => None,
+    }
+}
+
+/// Quotes arguments the way a POSIX shell would need them, so the command line can be copied
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c))
+    {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+fn status_icon(status: &str) -> &'static str {
+    match status {
+        "passed" => "✔",
+        "failed" | "unexpectedly-passed" => "✘",
+        "flaky" => "⚠",
+        _ => "○",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ansi_colors_become_spans() {
+        assert_eq!(
+            ansi_to_html("\x1b[1;31merror\x1b[0m: <missing> & more"),
+            "<span style=\"color:#cd3131;font-weight:bold;\">error</span>: &lt;missing&gt; &amp; more"
+        );
+        assert_eq!(
+            ansi_to_html("\x1b[32mgreen\x1b[44mon blue\x1b[0m"),
+            "<span style=\"color:#0dbc79;\">green</span><span style=\"color:#0dbc79;background:#2472c8;\">on blue</span>"
+        );
+    }
+
+    #[test]
+    fn ansi_sequences_besides_sgr_are_dropped() {
+        assert_eq!(ansi_to_html("\x1b[2K\x1b[1Gdone\x1b"), "done");
+        assert_eq!(ansi_to_html("\x1b[mplain"), "plain");
+    }
+
+    #[test]
+    fn extended_colors() {
+        let color = |codes: &[u8]| extended_color(&mut codes.iter().copied());
+
+        assert_eq!(color(&[5, 9]), Some("#f14c4c".to_owned()));
+        assert_eq!(color(&[5, 196]), Some("#ff0000".to_owned()));
+        assert_eq!(color(&[5, 16]), Some("#000000".to_owned()));
+        assert_eq!(color(&[5, 232]), Some("#080808".to_owned()));
+        assert_eq!(color(&[5, 255]), Some("#eeeeee".to_owned()));
+        assert_eq!(color(&[2, 1, 2, 255]), Some("#0102ff".to_owned()));
+        assert_eq!(color(&[2, 1]), None);
+        assert_eq!(color(&[3]), None);
+    }
+
+    #[test]
+    fn shell_quote_only_quotes_when_needed() {
+        assert_eq!(shell_quote("--flag=value"), "--flag=value");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("two words"), "'two words'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+}
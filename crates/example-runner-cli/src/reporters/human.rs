@@ -0,0 +1,98 @@
+use std::io::Write;
+
+use crate::{
+    execute_examples::RunResult,
+    run_report::{RunReport, Verdict},
+};
+
+use super::{Event, OutputStream, Reporter};
+
+pub struct HumanReporter;
+
+impl Reporter for HumanReporter {
+    fn report(&self, event: Event<'_>) {
+        match event {
+            Event::UnconfiguredExample { package, example } => {
+                println!("Unconfigured example found in crate {package}: {example}")
+            }
+            Event::UnknownExample { package, example } => {
+                println!("Unknown example configured in crate {package}: {example}")
+            }
+            Event::OutputLine {
+                names: (package, example, config),
+                stream,
+                line,
+            } => {
+                let line = format!("[{package} {example} <{config}>] {line}\n");
+                // Failing to print the output of an example shouldn't abort the whole run
+                let _ = match stream {
+                    OutputStream::Stdout => std::io::stdout().lock().write_all(line.as_bytes()),
+                    OutputStream::Stderr => std::io::stderr().lock().write_all(line.as_bytes()),
+                };
+            }
+            Event::Finished { reports } => {
+                for report in reports {
+                    print_summary(report);
+                }
+            }
+            Event::Planned { .. }
+            | Event::BuildStarted { .. }
+            | Event::BuildFinished { .. }
+            | Event::RunStarted { .. }
+            | Event::RunFinished { .. } => {}
+        }
+    }
+}
+
+fn print_summary(report: &RunReport) {
+    let (package, example, config_label) = &report.names;
+    let mut run = format!("[{package} {example} <{config_label}>]");
+    if !report.tags.is_empty() {
+        run.push_str(&format!(" (tags: {})", report.tags.join(", ")));
+    }
+
+    if let Some(path) = &report.blessed_snapshot {
+        println!("Example run {run} snapshot written to {}", path.display());
+    }
+
+    match (&report.verdict, &report.result) {
+        (Verdict::Failed(failures) | Verdict::UnexpectedPass { failures, .. }, _) => {
+            for failure in failures {
+                println!("Example run {run} {failure}");
+            }
+            if report.attempts > 1 {
+                println!(
+                    "Example run {run} failed in all {} attempts",
+                    report.attempts
+                );
+            }
+        }
+        (Verdict::Flaky(failures), _) => {
+            println!(
+                "Example run {run} is flaky, it suceeded after {} attempts",
+                report.attempts
+            );
+            for failure in failures {
+                println!("Example run {run} previously {failure}");
+            }
+        }
+        (Verdict::Ignored, _) => {
+            println!("Example run {run} ignored");
+        }
+        (Verdict::Skipped(reason), _) => {
+            println!("Example run {run} skipped ({reason})");
+        }
+        (Verdict::ExpectedFailure { reason, .. }, _) => {
+            println!("Example run {run} failed as expected ({reason})");
+        }
+        (Verdict::NotRun, _) => {
+            println!("Example run {run} not run");
+        }
+        (Verdict::Passed, RunResult::Built) => {
+            println!("Example run {run} built successfully");
+        }
+        (Verdict::Passed, _) => {
+            println!("Example run {run} suceeded!");
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::{
+    execute_examples::{ConfigurationNames, RunResult},
+    run_report::{RunReport, Verdict},
+};
+
+use super::{count, Event, Reporter};
+
+pub struct JsonReporter;
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+enum JsonEvent<'a> {
+    Planned {
+        runs: usize,
+        filtered_out: usize,
+    },
+    UnconfiguredExample {
+        package: &'a str,
+        example: &'a str,
+    },
+    UnknownExample {
+        package: &'a str,
+        example: &'a str,
+    },
+    BuildStarted {
+        package: &'a str,
+    },
+    BuildFinished {
+        package: &'a str,
+        success: bool,
+        /// In seconds
+        duration: f64,
+    },
+    RunStarted {
+        #[serde(flatten)]
+        run: JsonRun<'a>,
+    },
+    OutputLine {
+        #[serde(flatten)]
+        run: JsonRun<'a>,
+        stream: &'static str,
+        line: &'a str,
+    },
+    RunFinished {
+        #[serde(flatten)]
+        run: JsonRun<'a>,
+        tags: &'a [String],
+        result: &'static str,
+        exit_status: Option<i32>,
+        /// In seconds
+        duration: f64,
+        attempts: u32,
+        verdict: &'static str,
+        /// For flaky runs the failures of the previous attempt
+        failures: Vec<String>,
+        /// Why the run is expected to fail, for expected failures and unexpected passes
+        #[serde(skip_serializing_if = "Option::is_none")]
+        xfail: Option<&'a str>,
+        /// Why the run was skipped
+        #[serde(skip_serializing_if = "Option::is_none")]
+        skip_reason: Option<&'a str>,
+    },
+    Finished {
+        passed: usize,
+        failed: usize,
+        ignored: usize,
+        skipped: usize,
+        flaky: usize,
+        expected_failures: usize,
+        not_run: usize,
+    },
+}
+
+#[derive(Serialize)]
+struct JsonRun<'a> {
+    package: &'a str,
+    example: &'a str,
+    label: &'a str,
+}
+
+impl<'a> From<&'a ConfigurationNames> for JsonRun<'a> {
+    fn from((package, example, label): &'a ConfigurationNames) -> Self {
+        Self {
+            package,
+            example,
+            label,
+        }
+    }
+}
+
+impl<'a> From<&'a RunReport> for JsonEvent<'a> {
+    fn from(report: &'a RunReport) -> Self {
+        let (result, exit_status) = match &report.result {
+            RunResult::Exited(exit_status) => ("exited", exit_status.code()),
+            RunResult::TimedOut(_) => ("timed-out", None),
+            RunResult::Built => ("built", None),
+            RunResult::BuildFailed => ("build-failed", None),
+            RunResult::NoExecutable => ("no-executable", None),
+            RunResult::StdinReadFailed { .. } => ("stdin-read-failed", None),
+            RunResult::Ignored => ("ignored", None),
+            RunResult::Skipped(_) => ("skipped", None),
+            RunResult::NotRun => ("not-run", None),
+        };
+
+        JsonEvent::RunFinished {
+            run: (&report.names).into(),
+            tags: &report.tags,
+            result,
+            exit_status,
+            duration: report.duration.as_secs_f64(),
+            attempts: report.attempts,
+            verdict: report.verdict.as_str(),
+            failures: match &report.verdict {
+                Verdict::Failed(failures)
+                | Verdict::Flaky(failures)
+                | Verdict::ExpectedFailure { failures, .. }
+                | Verdict::UnexpectedPass { failures, .. } => {
+                    failures.iter().map(ToString::to_string).collect()
+                }
+                _ => Vec::new(),
+            },
+            xfail: match &report.verdict {
+                Verdict::ExpectedFailure { reason, .. }
+                | Verdict::UnexpectedPass { reason, .. } => Some(reason),
+                _ => None,
+            },
+            skip_reason: match &report.verdict {
+                Verdict::Skipped(reason) => Some(reason),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl Reporter for JsonReporter {
+    fn report(&self, event: Event<'_>) {
+        let event = match event {
+            Event::Planned { runs, filtered_out } => JsonEvent::Planned { runs, filtered_out },
+            Event::UnconfiguredExample { package, example } => {
+                JsonEvent::UnconfiguredExample { package, example }
+            }
+            Event::UnknownExample { package, example } => {
+                JsonEvent::UnknownExample { package, example }
+            }
+            Event::BuildStarted { package } => JsonEvent::BuildStarted { package },
+            Event::BuildFinished {
+                package,
+                success,
+                duration,
+            } => JsonEvent::BuildFinished {
+                package,
+                success,
+                duration: duration.as_secs_f64(),
+            },
+            Event::RunStarted { names } => JsonEvent::RunStarted { run: names.into() },
+            Event::OutputLine {
+                names,
+                stream,
+                line,
+            } => JsonEvent::OutputLine {
+                run: names.into(),
+                stream: stream.as_str(),
+                line,
+            },
+            Event::RunFinished { report } => report.into(),
+            Event::Finished { reports } => JsonEvent::Finished {
+                passed: count(reports, |verdict| matches!(verdict, Verdict::Passed)),
+                failed: count(reports, Verdict::is_failed),
+                ignored: count(reports, |verdict| matches!(verdict, Verdict::Ignored)),
+                skipped: count(reports, |verdict| matches!(verdict, Verdict::Skipped(_))),
+                flaky: count(reports, |verdict| matches!(verdict, Verdict::Flaky(_))),
+                expected_failures: count(reports, |verdict| {
+                    matches!(verdict, Verdict::ExpectedFailure { .. })
+                }),
+                not_run: count(reports, |verdict| matches!(verdict, Verdict::NotRun)),
+            },
+        };
+
+        let mut line = serde_json::to_string(&event).unwrap();
+        line.push('\n');
+        // Write the whole line at once so events of parallel runs don't interleave
+        let _ = std::io::stdout().lock().write_all(line.as_bytes());
+    }
+}
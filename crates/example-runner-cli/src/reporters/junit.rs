@@ -0,0 +1,184 @@
+use std::fmt::Write;
+
+use crate::{
+    config_args::ExpectedExitStatus,
+    run_report::{Failure, RunReport, Verdict},
+};
+
+use super::{count, escape, seconds};
+
+/// Renders a JUnit XML report with a `<testsuite>` per package and a `<testcase>` per run
+pub fn junit_report(reports: &[RunReport]) -> String {
+    let mut packages: Vec<(&str, Vec<&RunReport>)> = Vec::new();
+    for report in reports {
+        match packages
+            .iter_mut()
+            .find(|(package, _)| *package == report.names.0)
+        {
+            Some((_, package_reports)) => package_reports.push(report),
+            None => packages.push((&report.names.0, vec![report])),
+        }
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(
+        xml,
+        "<testsuites name=\"example-runner\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">",
+        reports.len(),
+        count(reports.iter(), Verdict::is_failed),
+        count(reports.iter(), |verdict| {
+            matches!(
+                verdict,
+                Verdict::Ignored
+                    | Verdict::Skipped(_)
+                    | Verdict::NotRun
+                    | Verdict::ExpectedFailure { .. }
+            )
+        }),
+        seconds(reports.iter().map(|report| report.duration).sum()),
+    )
+    .unwrap();
+
+    for (package, package_reports) in packages {
+        writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">",
+            escape(package),
+            package_reports.len(),
+            count(package_reports.iter().copied(), Verdict::is_failed),
+            count(package_reports.iter().copied(), |verdict| {
+                matches!(
+                    verdict,
+                    Verdict::Ignored
+                        | Verdict::Skipped(_)
+                        | Verdict::NotRun
+                        | Verdict::ExpectedFailure { .. }
+                )
+            }),
+            seconds(package_reports.iter().map(|report| report.duration).sum()),
+        )
+        .unwrap();
+
+        for report in package_reports {
+            write_testcase(&mut xml, report);
+        }
+
+        xml.push_str("  </testsuite>\n");
+    }
+
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn write_testcase(xml: &mut String, report: &RunReport) {
+    let (package, example, label) = &report.names;
+
+    writeln!(
+        xml,
+        "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\">",
+        escape(&format!("{example} <{label}>")),
+        escape(package),
+        seconds(report.duration),
+    )
+    .unwrap();
+
+    if !report.tags.is_empty() {
+        xml.push_str("      <properties>\n");
+        for tag in &report.tags {
+            writeln!(
+                xml,
+                "        <property name=\"tag\" value=\"{}\"/>",
+                escape(tag)
+            )
+            .unwrap();
+        }
+        xml.push_str("      </properties>\n");
+    }
+
+    match &report.verdict {
+        // Flaky runs use the element of the rerun extension of Maven Surefire
+        Verdict::Failed(failures)
+        | Verdict::Flaky(failures)
+        | Verdict::UnexpectedPass { failures, .. } => {
+            let element = if report.verdict.is_failed() {
+                "failure"
+            } else {
+                "flakyFailure"
+            };
+            writeln!(
+                xml,
+                "      <{element} message=\"{}\">{}</{element}>",
+                escape(
+                    &failures
+                        .iter()
+                        .map(failure_message)
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                ),
+                escape(
+                    &failures
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ),
+            )
+            .unwrap();
+        }
+        Verdict::Ignored => xml.push_str("      <skipped/>\n"),
+        Verdict::NotRun => xml.push_str("      <skipped message=\"not run\"/>\n"),
+        Verdict::Skipped(reason) => {
+            writeln!(xml, "      <skipped message=\"{}\"/>", escape(reason)).unwrap()
+        }
+        Verdict::ExpectedFailure { reason, .. } => writeln!(
+            xml,
+            "      <skipped message=\"{}\"/>",
+            escape(&format!("expected failure: {reason}"))
+        )
+        .unwrap(),
+        Verdict::Passed => {}
+    }
+
+    if !report.output.stdout.is_empty() {
+        writeln!(
+            xml,
+            "      <system-out>{}</system-out>",
+            escape(&report.output.stdout)
+        )
+        .unwrap();
+    }
+    if !report.output.stderr.is_empty() {
+        writeln!(
+            xml,
+            "      <system-err>{}</system-err>",
+            escape(&report.output.stderr)
+        )
+        .unwrap();
+    }
+
+    xml.push_str("    </testcase>\n");
+}
+
+/// A single line describing the failure, for the `message` attribute
+fn failure_message(failure: &Failure) -> String {
+    match failure {
+        Failure::ExitStatus { expected, actual } => {
+            let expected = match expected {
+                ExpectedExitStatus::Code(code) => format!("exit code {code}"),
+                group => group.to_string(),
+            };
+            // Examples killed by a signal have no exit code, the status then names the signal
+            let actual = match actual.code() {
+                Some(code) => format!("exit code {code}"),
+                None => actual.to_string(),
+            };
+            format!("expected {expected}, got {actual}")
+        }
+        failure => failure
+            .to_string()
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_owned(),
+    }
+}
@@ -0,0 +1,214 @@
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use serde::Serialize;
+
+use crate::{
+    execute_examples::ConfigurationNames,
+    run_report::{RunReport, Verdict},
+};
+
+use super::{count, Event, Reporter};
+
+/// Mimics the `--format json` output of libtest, so tools like IDE test explorers pick up examples
+/// as tests named `<package>::examples::<example>::<label>`
+#[derive(Default)]
+pub struct LibtestJsonReporter {
+    suite_start: Mutex<Option<Instant>>,
+    filtered_out: AtomicUsize,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum LibtestEvent {
+    Suite(SuiteEvent),
+    Test(TestEvent),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum SuiteEvent {
+    Started { test_count: usize },
+    Ok(SuiteSummary),
+    Failed(SuiteSummary),
+}
+
+#[derive(Serialize)]
+struct SuiteSummary {
+    passed: usize,
+    failed: usize,
+    ignored: usize,
+    measured: usize,
+    filtered_out: usize,
+    exec_time: f64,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum TestEvent {
+    Started {
+        name: String,
+    },
+    // libtest doesn't know tags, consumers ignore unknown fields though
+    Ok {
+        name: String,
+        exec_time: f64,
+        /// The failures of the previous attempt of flaky runs, or why expected failures fail
+        #[serde(skip_serializing_if = "Option::is_none")]
+        stdout: Option<String>,
+        tags: Vec<String>,
+    },
+    Failed {
+        name: String,
+        exec_time: f64,
+        stdout: String,
+        tags: Vec<String>,
+    },
+    Ignored {
+        name: String,
+        /// Why the test was skipped, like the reason of `#[ignore = "reason"]`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        tags: Vec<String>,
+    },
+}
+
+fn test_name((package, example, label): &ConfigurationNames) -> String {
+    format!("{package}::examples::{example}::{label}")
+}
+
+impl Reporter for LibtestJsonReporter {
+    fn report(&self, event: Event<'_>) {
+        let event = match event {
+            Event::Planned { runs, filtered_out } => {
+                *self.suite_start.lock().unwrap() = Some(Instant::now());
+                self.filtered_out.store(filtered_out, Ordering::Relaxed);
+                LibtestEvent::Suite(SuiteEvent::Started { test_count: runs })
+            }
+            Event::RunStarted { names } => LibtestEvent::Test(TestEvent::Started {
+                name: test_name(names),
+            }),
+            Event::OutputLine {
+                names: (package, example, config),
+                stream,
+                line,
+            } => {
+                // stdout belongs to the event stream, so the output of examples goes to stderr
+                let _ = std::io::stderr().lock().write_all(
+                    format!(
+                        "[{package} {example} <{config}>] {}: {line}\n",
+                        stream.as_str()
+                    )
+                    .as_bytes(),
+                );
+                return;
+            }
+            Event::RunFinished { report } => LibtestEvent::Test(test_finished(report)),
+            Event::Finished { reports } => {
+                let summary = SuiteSummary {
+                    passed: count(reports, |verdict| {
+                        matches!(
+                            verdict,
+                            Verdict::Passed | Verdict::Flaky(_) | Verdict::ExpectedFailure { .. }
+                        )
+                    }),
+                    failed: count(reports, Verdict::is_failed),
+                    ignored: count(reports, |verdict| {
+                        matches!(
+                            verdict,
+                            Verdict::Ignored | Verdict::Skipped(_) | Verdict::NotRun
+                        )
+                    }),
+                    measured: 0,
+                    filtered_out: self.filtered_out.load(Ordering::Relaxed),
+                    exec_time: self
+                        .suite_start
+                        .lock()
+                        .unwrap()
+                        .map(|start| start.elapsed().as_secs_f64())
+                        .unwrap_or_default(),
+                };
+
+                LibtestEvent::Suite(if summary.failed > 0 {
+                    SuiteEvent::Failed(summary)
+                } else {
+                    SuiteEvent::Ok(summary)
+                })
+            }
+            Event::UnconfiguredExample { .. }
+            | Event::UnknownExample { .. }
+            | Event::BuildStarted { .. }
+            | Event::BuildFinished { .. } => return,
+        };
+
+        let mut line = serde_json::to_string(&event).unwrap();
+        line.push('\n');
+        let _ = std::io::stdout().lock().write_all(line.as_bytes());
+    }
+}
+
+fn test_finished(report: &RunReport) -> TestEvent {
+    let name = test_name(&report.names);
+    let exec_time = report.duration.as_secs_f64();
+    let tags = report.tags.clone();
+
+    match &report.verdict {
+        Verdict::Passed => TestEvent::Ok {
+            name,
+            exec_time,
+            stdout: None,
+            tags,
+        },
+        Verdict::ExpectedFailure { reason, .. } => TestEvent::Ok {
+            name,
+            exec_time,
+            stdout: Some(format!("failed as expected ({reason})\n")),
+            tags,
+        },
+        Verdict::Flaky(failures) => {
+            let mut stdout = format!("flaky, passed after {} attempts\n", report.attempts);
+            for failure in failures {
+                stdout.push_str(&format!("{failure}\n"));
+            }
+
+            TestEvent::Ok {
+                name,
+                exec_time,
+                stdout: Some(stdout),
+                tags,
+            }
+        }
+        // libtest has no notion of cancelled tests
+        Verdict::Ignored | Verdict::NotRun => TestEvent::Ignored {
+            name,
+            message: None,
+            tags,
+        },
+        Verdict::Skipped(reason) => TestEvent::Ignored {
+            name,
+            message: Some(reason.clone()),
+            tags,
+        },
+        Verdict::Failed(failures) | Verdict::UnexpectedPass { failures, .. } => {
+            // libtest puts the panic message into the captured output, so do the same for failures
+            let mut stdout = report.output.stdout.clone();
+            stdout.push_str(&report.output.stderr);
+            for failure in failures {
+                stdout.push_str(&format!("{failure}\n"));
+            }
+
+            TestEvent::Failed {
+                name,
+                exec_time,
+                stdout,
+                tags,
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+mod github;
+mod html;
+mod human;
+mod json;
+mod junit;
+mod libtest_json;
+mod tap;
+
+use std::{sync::Arc, time::Duration};
+
+use clap::ValueEnum;
+
+use crate::{
+    execute_examples::ConfigurationNames,
+    run_report::{RunReport, Verdict},
+};
+
+pub use github::step_summary;
+pub use html::html_report;
+pub use junit::junit_report;
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MessageFormat {
+    /// Human readable output, with the output of examples prefixed by their name
+    #[default]
+    Human,
+    /// One JSON event per line
+    Json,
+    /// The Test Anything Protocol, with the output of examples printed to stderr
+    Tap,
+    /// The JSON events of libtest (`cargo test -- --format json`), with the output of examples
+    /// printed to stderr
+    LibtestJson,
+    /// Human readable output plus GitHub Actions annotations for failed runs, the default when
+    /// `GITHUB_ACTIONS` is set
+    Github,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+impl OutputStream {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputStream::Stdout => "stdout",
+            OutputStream::Stderr => "stderr",
+        }
+    }
+}
+
+pub enum Event<'a> {
+    /// The number of configurations that will be reported on, and of those deselected
+    Planned {
+        runs: usize,
+        filtered_out: usize,
+    },
+    UnconfiguredExample {
+        package: &'a str,
+        example: &'a str,
+    },
+    UnknownExample {
+        package: &'a str,
+        example: &'a str,
+    },
+    BuildStarted {
+        package: &'a str,
+    },
+    BuildFinished {
+        package: &'a str,
+        success: bool,
+        duration: Duration,
+    },
+    RunStarted {
+        names: &'a ConfigurationNames,
+    },
+    OutputLine {
+        names: &'a ConfigurationNames,
+        stream: OutputStream,
+        line: &'a str,
+    },
+    RunFinished {
+        report: &'a RunReport,
+    },
+    /// All runs are done, with their reports in declaration order
+    Finished {
+        reports: &'a [RunReport],
+    },
+}
+
+/// Presents the progress and results of a run on stdout
+pub trait Reporter: Send + Sync {
+    fn report(&self, event: Event<'_>);
+}
+
+pub fn reporter(message_format: MessageFormat) -> Arc<dyn Reporter> {
+    match message_format {
+        MessageFormat::Human => Arc::new(human::HumanReporter),
+        MessageFormat::Json => Arc::new(json::JsonReporter),
+        MessageFormat::Tap => Arc::new(tap::TapReporter::default()),
+        MessageFormat::LibtestJson => Arc::new(libtest_json::LibtestJsonReporter::default()),
+        MessageFormat::Github => Arc::new(github::GithubReporter::new()),
+    }
+}
+
+/// The number of runs with a verdict matching the filter
+fn count<'a>(
+    reports: impl IntoIterator<Item = &'a RunReport>,
+    filter: impl Fn(&Verdict) -> bool,
+) -> usize {
+    reports
+        .into_iter()
+        .filter(|report| filter(&report.verdict))
+        .count()
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+/// Escapes text for use in XML and HTML content and attributes, replacing characters XML can't
+/// represent
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => escaped.push(char::REPLACEMENT_CHARACTER),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_markup_and_quotes() {
+        assert_eq!(
+            escape(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &apos;Jerry&apos;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn escape_replaces_control_characters() {
+        assert_eq!(escape("tab\tline\r\n"), "tab\tline\r\n");
+        assert_eq!(
+            escape("bell\x07 escape\x1b[0m"),
+            "bell\u{fffd} escape\u{fffd}[0m"
+        );
+    }
+}
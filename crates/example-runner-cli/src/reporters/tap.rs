@@ -0,0 +1,162 @@
+use std::{
+    io::Write,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::{
+    execute_examples::RunResult,
+    run_report::{Failure, RunReport, Verdict},
+};
+
+use super::{Event, Reporter};
+
+/// How many lines of stderr are included in the diagnostics of a failed run
+const STDERR_TAIL_LINES: usize = 10;
+
+#[derive(Default)]
+pub struct TapReporter {
+    test_number: AtomicUsize,
+    /// The version line has to come first, even before comments about the configuration
+    version_written: AtomicBool,
+}
+
+impl Reporter for TapReporter {
+    fn report(&self, event: Event<'_>) {
+        let text = match event {
+            Event::Planned { runs, .. } => format!("1..{runs}\n"),
+            Event::UnconfiguredExample { package, example } => {
+                format!("# Unconfigured example found in crate {package}: {example}\n")
+            }
+            Event::UnknownExample { package, example } => {
+                format!("# Unknown example configured in crate {package}: {example}\n")
+            }
+            Event::OutputLine {
+                names: (package, example, config),
+                stream,
+                line,
+            } => {
+                // stdout belongs to the TAP stream, so the output of examples goes to stderr
+                let _ = std::io::stderr().lock().write_all(
+                    format!(
+                        "[{package} {example} <{config}>] {}: {line}\n",
+                        stream.as_str()
+                    )
+                    .as_bytes(),
+                );
+                return;
+            }
+            Event::RunFinished { report } => self.test_point(report),
+            Event::BuildStarted { .. }
+            | Event::BuildFinished { .. }
+            | Event::RunStarted { .. }
+            | Event::Finished { .. } => return,
+        };
+
+        let mut stdout = std::io::stdout().lock();
+        if !self.version_written.swap(true, Ordering::Relaxed) {
+            let _ = stdout.write_all(b"TAP version 13\n");
+        }
+        let _ = stdout.write_all(text.as_bytes());
+    }
+}
+
+impl TapReporter {
+    fn test_point(&self, report: &RunReport) -> String {
+        let number = self.test_number.fetch_add(1, Ordering::Relaxed) + 1;
+        let (package, example, label) = &report.names;
+        let mut description = format!("{package} {example} <{label}>");
+        if !report.tags.is_empty() {
+            description.push_str(&format!(" (tags: {})", report.tags.join(", ")));
+        }
+
+        match &report.verdict {
+            Verdict::Passed => format!("ok {number} - {description}\n"),
+            Verdict::Ignored => format!("ok {number} - {description} # SKIP ignored\n"),
+            Verdict::NotRun => format!("ok {number} - {description} # SKIP not run\n"),
+            Verdict::Skipped(reason) => format!("ok {number} - {description} # SKIP {reason}\n"),
+            // TODO is how TAP marks tests which are expected to fail
+            Verdict::ExpectedFailure { reason, .. } => {
+                format!("not ok {number} - {description} # TODO {reason}\n")
+            }
+            Verdict::Flaky(failures) => format!(
+                "ok {number} - {description} (flaky)\n  ---\n  message: {}\n  attempts: {}\n  ...\n",
+                yaml_string(&failure_messages(failures)),
+                report.attempts
+            ),
+            Verdict::Failed(failures) | Verdict::UnexpectedPass { failures, .. } => {
+                let mut text = format!("not ok {number} - {description}\n  ---\n");
+                text.push_str(&format!(
+                    "  message: {}\n",
+                    yaml_string(&failure_messages(failures))
+                ));
+                if report.attempts > 1 {
+                    text.push_str(&format!("  attempts: {}\n", report.attempts));
+                }
+                if let RunResult::Exited(exit_status) = &report.result {
+                    match exit_status.code() {
+                        Some(code) => text.push_str(&format!("  exit_status: {code}\n")),
+                        None => text.push_str(&format!(
+                            "  exit_status: {}\n",
+                            yaml_string(&exit_status.to_string())
+                        )),
+                    }
+                }
+                text.push_str(&format!("  duration_ms: {}\n", report.duration.as_millis()));
+                text.push_str(&stderr_tail(&report.output.stderr));
+                text.push_str("  ...\n");
+                text
+            }
+        }
+    }
+}
+
+/// The last lines of stderr as YAML block scalar, or nothing without any stderr
+fn stderr_tail(stderr: &str) -> String {
+    let lines = stderr.lines().collect::<Vec<_>>();
+    if lines.is_empty() {
+        return String::new();
+    }
+
+    // Without the indentation indicator YAML takes the indentation of the first line, so a first
+    // line that is indented itself would break the block
+    let mut text = "  stderr: |2\n".to_owned();
+    for line in &lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..] {
+        text.push_str(&format!("    {line}\n"));
+    }
+    text
+}
+
+fn failure_messages(failures: &[Failure]) -> String {
+    failures
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// JSON strings are valid double quoted YAML scalars
+fn yaml_string(text: &str) -> String {
+    serde_json::to_string(text).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stderr_tail_keeps_the_indentation() {
+        assert_eq!(
+            stderr_tail("  indented\nnot indented\n"),
+            "  stderr: |2\n      indented\n    not indented\n"
+        );
+        assert_eq!(stderr_tail(""), "");
+    }
+
+    #[test]
+    fn stderr_tail_has_the_last_lines() {
+        let stderr = (1..=12).map(|line| format!("{line}\n")).collect::<String>();
+
+        assert_eq!(stderr_tail(&stderr).lines().nth(1), Some("    3"));
+        assert_eq!(stderr_tail(&stderr).lines().count(), STDERR_TAIL_LINES + 1);
+    }
+}
@@ -0,0 +1,256 @@
+use std::{env, path::Path};
+
+use miette::Diagnostic;
+use rust_metadata::cfg::{parse_cfgs, Cfg, CfgParseError};
+use serde::Deserialize;
+
+use crate::{config_args::FinalizedConfigArgs, output_assertions::one_or_many};
+
+/// Conditions on the environment of the runner, used for `skip_if` and `only_if`
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RunConditions {
+    /// Environment variables that are set
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub env: Vec<String>,
+    /// Environment variables that aren't set
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub env_unset: Vec<String>,
+    /// Commands that can be found on the `PATH`
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub command: Vec<String>,
+    /// Files relative to the directory of the package `Cargo.toml`
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub file_exists: Vec<String>,
+}
+
+impl RunConditions {
+    /// Checks every condition, returning whether it holds together with a description of the
+    /// actual state
+    fn check(&self, manifest_dir: &Path) -> Vec<(bool, String)> {
+        let mut checked = Vec::new();
+
+        for key in &self.env {
+            checked.push(env_condition(key, true));
+        }
+        for key in &self.env_unset {
+            checked.push(env_condition(key, false));
+        }
+        for command in &self.command {
+            checked.push(if is_on_path(command) {
+                (true, format!("`{command}` is on the PATH"))
+            } else {
+                (false, format!("`{command}` is not on the PATH"))
+            });
+        }
+        for file in &self.file_exists {
+            checked.push(if manifest_dir.join(file).exists() {
+                (true, format!("`{file}` exists"))
+            } else {
+                (false, format!("`{file}` doesn't exist"))
+            });
+        }
+
+        checked
+    }
+}
+
+#[derive(thiserror::Error, Debug, Diagnostic)]
+pub enum TargetCfgError {
+    #[diagnostic(
+        code(target_cfg::rustc),
+        help("Check that `rustc` is installed, or set `RUSTC` to its path")
+    )]
+    #[error("Failed to run `rustc --print cfg`")]
+    Rustc(#[source] std::io::Error),
+    #[diagnostic(
+        code(target_cfg::failed),
+        help("Check the target passed with `--cfg-target`")
+    )]
+    #[error("`rustc --print cfg` failed:\n{stderr}")]
+    Failed { stderr: String },
+    #[diagnostic(transparent)]
+    #[error(transparent)]
+    Parse(#[from] CfgParseError),
+}
+
+/// The cfg options of the target from `rustc --print cfg`, or of the host if no target is given
+pub async fn target_cfg(target: Option<&str>) -> Result<Vec<Cfg>, TargetCfgError> {
+    let mut command = tokio::process::Command::new(env::var_os("RUSTC").unwrap_or("rustc".into()));
+    command.args(["--print", "cfg"]);
+    if let Some(target) = target {
+        command.args(["--target", target]);
+    }
+
+    let output = command.output().await.map_err(TargetCfgError::Rustc)?;
+    if !output.status.success() {
+        return Err(TargetCfgError::Failed {
+            stderr: String::from_utf8_lossy(&output.stderr)
+                .trim_end()
+                .to_owned(),
+        });
+    }
+
+    Ok(parse_cfgs(&String::from_utf8_lossy(&output.stdout))?)
+}
+
+/// Why a configuration is skipped: because the target doesn't match `only_on`, any condition of
+/// `skip_if` holds or any condition of `only_if` doesn't
+pub fn skip_reason(
+    args: &FinalizedConfigArgs,
+    manifest_dir: &Path,
+    target_cfg: &[Cfg],
+) -> Option<String> {
+    let mut reasons = Vec::new();
+
+    if let Some(only_on) = &args.only_on {
+        if !only_on.0.matches(target_cfg) {
+            reasons.push(format!("only runs on `cfg({})`", only_on.0));
+        }
+    }
+    if let Some(skip_if) = &args.skip_if {
+        reasons.extend(
+            skip_if
+                .check(manifest_dir)
+                .into_iter()
+                .filter(|(holds, _)| *holds)
+                .map(|(_, reason)| reason),
+        );
+    }
+    if let Some(only_if) = &args.only_if {
+        reasons.extend(
+            only_if
+                .check(manifest_dir)
+                .into_iter()
+                .filter(|(holds, _)| !*holds)
+                .map(|(_, reason)| reason),
+        );
+    }
+
+    (!reasons.is_empty()).then(|| reasons.join(", "))
+}
+
+fn env_condition(key: &str, expect_set: bool) -> (bool, String) {
+    let is_set = env::var_os(key).is_some();
+    let reason = if is_set {
+        format!("`{key}` is set")
+    } else {
+        format!("`{key}` is not set")
+    };
+
+    (is_set == expect_set, reason)
+}
+
+fn is_on_path(command: &str) -> bool {
+    let Some(path) = env::var_os("PATH") else {
+        return false;
+    };
+
+    env::split_paths(&path).any(|directory| {
+        let mut candidate = directory.join(command).into_os_string();
+        // Executables have to be named with their extension on some platforms, e.g. `.exe`, which
+        // the command may already have
+        if !command.ends_with(env::consts::EXE_SUFFIX) {
+            candidate.push(env::consts::EXE_SUFFIX);
+        }
+        is_executable(Path::new(&candidate))
+    })
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_metadata::cfg::CfgExpr;
+
+    use crate::config_args::CfgExpression;
+
+    use super::*;
+
+    const UNSET: &str = "EXAMPLE_RUNNER_TEST_UNSET";
+
+    fn manifest_dir() -> &'static Path {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn conditions(env: &[&str], file_exists: &[&str]) -> Option<RunConditions> {
+        Some(RunConditions {
+            env: env.iter().map(ToString::to_string).collect(),
+            file_exists: file_exists.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        })
+    }
+
+    fn target_cfg() -> Vec<Cfg> {
+        parse_cfgs("unix\ntarget_os=\"linux\"").unwrap()
+    }
+
+    #[test]
+    fn runs_without_conditions() {
+        let args = FinalizedConfigArgs::default();
+
+        assert_eq!(skip_reason(&args, manifest_dir(), &target_cfg()), None);
+    }
+
+    #[test]
+    fn only_on_matches_the_target() {
+        let only_on = |expression| FinalizedConfigArgs {
+            only_on: Some(CfgExpression(CfgExpr::parse(expression).unwrap())),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            skip_reason(&only_on("cfg(unix)"), manifest_dir(), &target_cfg()),
+            None
+        );
+        assert_eq!(
+            skip_reason(&only_on("cfg(windows)"), manifest_dir(), &target_cfg()),
+            Some("only runs on `cfg(windows)`".to_owned())
+        );
+    }
+
+    #[test]
+    fn skip_if_reports_the_conditions_that_hold() {
+        let args = FinalizedConfigArgs {
+            skip_if: conditions(&["PATH", UNSET], &["Cargo.toml", "missing.toml"]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            skip_reason(&args, manifest_dir(), &target_cfg()),
+            Some("`PATH` is set, `Cargo.toml` exists".to_owned())
+        );
+    }
+
+    #[test]
+    fn only_if_reports_the_conditions_that_dont_hold() {
+        let args = FinalizedConfigArgs {
+            only_if: conditions(&["PATH", UNSET], &["Cargo.toml", "missing.toml"]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            skip_reason(&args, manifest_dir(), &target_cfg()),
+            Some(format!(
+                "`{UNSET}` is not set, `missing.toml` doesn't exist"
+            ))
+        );
+    }
+
+    #[test]
+    fn finds_commands_on_the_path() {
+        assert!(!is_on_path("example-runner-missing-command"));
+        #[cfg(unix)]
+        assert!(is_on_path("sh"));
+    }
+}
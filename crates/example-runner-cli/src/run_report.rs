@@ -0,0 +1,434 @@
+use std::{fmt::Display, path::PathBuf, process::ExitStatus, time::Duration};
+
+use cargo_metadata::{Metadata, Package};
+
+use crate::{
+    config_args::{ExpectedExitStatus, FinalizedConfigArgs},
+    example_config::ExampleConfigFinalized,
+    execute_examples::{CapturedOutput, ConfigurationNames, Invocation, RunResult},
+    output_filters::OutputFilters,
+    snapshots::{check_snapshot, snapshot_path, SnapshotOutcome},
+};
+
+/// Everything known about a configuration after it was run
+pub struct RunReport {
+    pub names: ConfigurationNames,
+    pub tags: Vec<String>,
+    /// The source file of the example
+    pub source_path: PathBuf,
+    pub result: RunResult,
+    /// How the example was run, if it was run at all
+    pub invocation: Option<Invocation>,
+    /// The captured output as the example printed it, the output filters only apply to the
+    /// assertions and the snapshot
+    pub output: CapturedOutput,
+    pub duration: Duration,
+    /// How often the example was run, more than once if failed runs were retried
+    pub attempts: u32,
+    pub verdict: Verdict,
+    /// The snapshot file that was written because of `--bless`
+    pub blessed_snapshot: Option<PathBuf>,
+}
+
+pub enum Verdict {
+    Passed,
+    Failed(Vec<Failure>),
+    Ignored,
+    /// Skipped because of `skip_if` or `only_if`, with the reason
+    Skipped(String),
+    /// Passed after failing before, with the failures of the previous attempt
+    Flaky(Vec<Failure>),
+    /// Failed as expected because of `xfail`
+    ExpectedFailure {
+        reason: String,
+        failures: Vec<Failure>,
+    },
+    /// Passed although it is marked as `xfail`, which counts as failed
+    UnexpectedPass {
+        reason: String,
+        failures: Vec<Failure>,
+    },
+    /// Skipped or cancelled because of `--fail-fast` or `--max-fail`
+    NotRun,
+}
+
+impl Verdict {
+    pub fn is_failed(&self) -> bool {
+        matches!(self, Verdict::Failed(_) | Verdict::UnexpectedPass { .. })
+    }
+
+    /// Turns failures into expected ones and passes into failures if the configuration is marked
+    /// as `xfail` with the given reason
+    fn with_xfail(self, xfail: Option<&str>, result: &RunResult) -> Self {
+        // Only the run itself is expected to fail, an example failing to build is still an error
+        match (xfail, self, result) {
+            (
+                Some(reason),
+                Verdict::Failed(failures),
+                RunResult::Exited(_) | RunResult::TimedOut(_),
+            ) => Verdict::ExpectedFailure {
+                reason: reason.to_owned(),
+                failures,
+            },
+            (Some(reason), Verdict::Passed, RunResult::Exited(_)) => Verdict::UnexpectedPass {
+                reason: reason.to_owned(),
+                failures: vec![Failure::UnexpectedPass(reason.to_owned())],
+            },
+            (_, verdict, _) => verdict,
+        }
+    }
+
+    /// Whether the run gets another attempt after the given number of attempts
+    pub fn is_retried(&self, args: &FinalizedConfigArgs, attempts: u32) -> bool {
+        // An `xfail` configuration only fails if it passes, so retrying it would hide that
+        self.is_failed() && args.xfail.is_none() && attempts <= args.retries
+    }
+
+    /// The verdict of a retry, which is flaky if it passed after the previous attempt failed
+    pub fn after_retry(self, previous: Verdict) -> Self {
+        match (self, previous) {
+            (Verdict::Passed, Verdict::Failed(failures)) => Verdict::Flaky(failures),
+            (verdict, _) => verdict,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Verdict::Passed => "passed",
+            Verdict::Failed(_) => "failed",
+            Verdict::Ignored => "ignored",
+            Verdict::Skipped(_) => "skipped",
+            Verdict::Flaky(_) => "flaky",
+            Verdict::ExpectedFailure { .. } => "expected-failure",
+            Verdict::UnexpectedPass { .. } => "unexpectedly-passed",
+            Verdict::NotRun => "not-run",
+        }
+    }
+}
+
+pub enum Failure {
+    BuildFailed,
+    NoExecutable,
+    StdinRead {
+        path: PathBuf,
+        error: String,
+    },
+    TimedOut(Duration),
+    ExitStatus {
+        expected: ExpectedExitStatus,
+        actual: ExitStatus,
+    },
+    Assertions {
+        stream: &'static str,
+        report: String,
+    },
+    SnapshotMissing(PathBuf),
+    SnapshotChanged {
+        path: PathBuf,
+        diff: String,
+    },
+    SnapshotAccess {
+        path: PathBuf,
+        error: String,
+    },
+    /// The run passed although it is marked as `xfail` with the given reason
+    UnexpectedPass(String),
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::BuildFailed => write!(f, "failed to build!"),
+            Failure::NoExecutable => write!(
+                f,
+                "compiled without an executable, only examples of the `bin` crate type can run, make it `no_run`!"
+            ),
+            Failure::StdinRead { path, error } => {
+                write!(f, "couldn't read the stdin file {}: {error}!", path.display())
+            }
+            Failure::TimedOut(timeout) => write!(
+                f,
+                "timed out after {}!",
+                humantime::format_duration(*timeout)
+            ),
+            Failure::ExitStatus { actual, .. } => {
+                write!(f, "executed unsucessfully with {actual}!")
+            }
+            Failure::Assertions { stream, report } => {
+                write!(f, "{stream} assertions failed:\n{}", report.trim_end())
+            }
+            Failure::SnapshotMissing(path) => write!(
+                f,
+                "has no snapshot at {}, run with `--bless` to create it!",
+                path.display()
+            ),
+            Failure::SnapshotChanged { path, diff } => write!(
+                f,
+                "output doesn't match the snapshot at {}:\n{}",
+                path.display(),
+                diff.trim_end()
+            ),
+            Failure::SnapshotAccess { path, error } => write!(
+                f,
+                "couldn't access the snapshot at {}: {error}!",
+                path.display()
+            ),
+            Failure::UnexpectedPass(reason) => write!(
+                f,
+                "unexpectedly passed although it is expected to fail ({reason}), remove its `xfail`!"
+            ),
+        }
+    }
+}
+
+/// Decides whether runs met the expectations of their configuration
+pub struct Evaluator<'a> {
+    metadata: &'a Metadata,
+    output_filters: OutputFilters<'a>,
+    bless: bool,
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(metadata: &'a Metadata, bless: bool) -> Self {
+        Self {
+            metadata,
+            output_filters: OutputFilters::new(metadata),
+            bless,
+        }
+    }
+
+    pub async fn evaluate(
+        &self,
+        names: ConfigurationNames,
+        configuration: ExampleConfigFinalized,
+        result: RunResult,
+        invocation: Option<Invocation>,
+        output: CapturedOutput,
+        duration: Duration,
+    ) -> RunReport {
+        let filtered = self
+            .output_filters
+            .apply(&configuration.args.filters, &output);
+        let mut failures = Vec::new();
+        let mut blessed_snapshot = None;
+
+        let verdict = match &result {
+            RunResult::Ignored => Verdict::Ignored,
+            RunResult::Skipped(reason) => Verdict::Skipped(reason.clone()),
+            RunResult::NotRun => Verdict::NotRun,
+            RunResult::Built => Verdict::Passed,
+            RunResult::BuildFailed => Verdict::Failed(vec![Failure::BuildFailed]),
+            RunResult::NoExecutable => Verdict::Failed(vec![Failure::NoExecutable]),
+            RunResult::StdinReadFailed { path, error } => {
+                Verdict::Failed(vec![Failure::StdinRead {
+                    path: path.clone(),
+                    error: error.clone(),
+                }])
+            }
+            RunResult::TimedOut(timeout) => Verdict::Failed(vec![Failure::TimedOut(*timeout)]),
+            RunResult::Exited(exit_status) => {
+                if !configuration.args.expected_exit_status.matches(exit_status) {
+                    failures.push(Failure::ExitStatus {
+                        expected: configuration.args.expected_exit_status.clone(),
+                        actual: *exit_status,
+                    });
+                }
+
+                for (stream, assertions, captured) in [
+                    (
+                        "stdout",
+                        &configuration.args.expected_stdout,
+                        &filtered.stdout,
+                    ),
+                    (
+                        "stderr",
+                        &configuration.args.expected_stderr,
+                        &filtered.stderr,
+                    ),
+                ] {
+                    if let Some(report) = assertions
+                        .as_ref()
+                        .and_then(|assertions| assertions.check(captured))
+                    {
+                        failures.push(Failure::Assertions { stream, report });
+                    }
+                }
+
+                // Blessing the output of a failed run would store broken output, so leave the
+                // snapshot alone until the run passes
+                if configuration.args.snapshot && (failures.is_empty() || !self.bless) {
+                    let path = self.snapshot_path(&names);
+                    match check_snapshot(&path, &filtered, self.bless).await {
+                        Ok(SnapshotOutcome::Matched) => {}
+                        Ok(SnapshotOutcome::Blessed) => blessed_snapshot = Some(path),
+                        Ok(SnapshotOutcome::Missing) => {
+                            failures.push(Failure::SnapshotMissing(path))
+                        }
+                        Ok(SnapshotOutcome::Changed(diff)) => {
+                            failures.push(Failure::SnapshotChanged { path, diff })
+                        }
+                        Err(error) => failures.push(Failure::SnapshotAccess {
+                            path,
+                            error: error.to_string(),
+                        }),
+                    }
+                }
+
+                if failures.is_empty() {
+                    Verdict::Passed
+                } else {
+                    Verdict::Failed(failures)
+                }
+            }
+        };
+
+        let verdict = verdict.with_xfail(configuration.args.xfail.as_deref(), &result);
+
+        RunReport {
+            tags: configuration.args.tags.clone(),
+            source_path: self.source_path(&names),
+            names,
+            result,
+            invocation,
+            output,
+            duration,
+            attempts: 1,
+            verdict,
+            blessed_snapshot,
+        }
+    }
+
+    fn package(&self, name: &str) -> &'a Package {
+        // Package names are unique within a workspace
+        self.metadata
+            .workspace_packages()
+            .into_iter()
+            .find(|package| package.name == name)
+            .unwrap()
+    }
+
+    fn source_path(&self, (package, example, _): &ConfigurationNames) -> PathBuf {
+        self.package(package)
+            .targets
+            .iter()
+            .find(|target| target.is_example() && &target.name == example)
+            .unwrap()
+            .src_path
+            .clone()
+            .into()
+    }
+
+    fn snapshot_path(&self, names: &ConfigurationNames) -> PathBuf {
+        let manifest_path = &self.package(&names.0).manifest_path;
+
+        snapshot_path(
+            manifest_path
+                .parent()
+                .unwrap_or(manifest_path)
+                .as_std_path(),
+            names,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exited() -> RunResult {
+        #[cfg(unix)]
+        use std::os::unix::process::ExitStatusExt;
+        #[cfg(windows)]
+        use std::os::windows::process::ExitStatusExt;
+
+        RunResult::Exited(ExitStatus::from_raw(0))
+    }
+
+    fn failed() -> Verdict {
+        Verdict::Failed(vec![Failure::TimedOut(Duration::from_secs(1))])
+    }
+
+    #[test]
+    fn xfail_expects_the_run_to_fail() {
+        assert!(matches!(
+            failed().with_xfail(Some("bug"), &exited()),
+            Verdict::ExpectedFailure { reason, failures } if reason == "bug" && failures.len() == 1
+        ));
+        assert!(matches!(
+            failed().with_xfail(Some("bug"), &RunResult::TimedOut(Duration::from_secs(1))),
+            Verdict::ExpectedFailure { .. }
+        ));
+        assert!(matches!(
+            Verdict::Passed.with_xfail(Some("bug"), &exited()),
+            Verdict::UnexpectedPass { reason, failures }
+                if reason == "bug" && matches!(&failures[..], [Failure::UnexpectedPass(_)])
+        ));
+        assert!(Verdict::Passed
+            .with_xfail(Some("bug"), &exited())
+            .is_failed());
+    }
+
+    #[test]
+    fn xfail_doesnt_excuse_build_failures() {
+        let build_failed = Verdict::Failed(vec![Failure::BuildFailed]);
+
+        assert!(build_failed
+            .with_xfail(Some("bug"), &RunResult::BuildFailed)
+            .is_failed());
+        assert!(matches!(
+            Verdict::Passed.with_xfail(Some("bug"), &RunResult::Built),
+            Verdict::Passed
+        ));
+        assert!(matches!(
+            Verdict::Ignored.with_xfail(Some("bug"), &RunResult::Ignored),
+            Verdict::Ignored
+        ));
+    }
+
+    #[test]
+    fn without_xfail_the_verdict_stays() {
+        assert!(failed().with_xfail(None, &exited()).is_failed());
+        assert!(matches!(
+            Verdict::Passed.with_xfail(None, &exited()),
+            Verdict::Passed
+        ));
+    }
+
+    #[test]
+    fn failed_runs_are_retried() {
+        let args = FinalizedConfigArgs {
+            retries: 2,
+            ..Default::default()
+        };
+
+        assert!(failed().is_retried(&args, 1));
+        assert!(failed().is_retried(&args, 2));
+        assert!(!failed().is_retried(&args, 3));
+        assert!(!Verdict::Passed.is_retried(&args, 1));
+        assert!(!failed().is_retried(&FinalizedConfigArgs::default(), 1));
+    }
+
+    #[test]
+    fn xfail_runs_arent_retried() {
+        let args = FinalizedConfigArgs {
+            retries: 2,
+            xfail: Some("bug".to_owned()),
+            ..Default::default()
+        };
+        let unexpected_pass = Verdict::UnexpectedPass {
+            reason: "bug".to_owned(),
+            failures: vec![Failure::UnexpectedPass("bug".to_owned())],
+        };
+
+        assert!(!unexpected_pass.is_retried(&args, 1));
+    }
+
+    #[test]
+    fn passing_retries_are_flaky() {
+        assert!(matches!(
+            Verdict::Passed.after_retry(failed()),
+            Verdict::Flaky(failures) if matches!(&failures[..], [Failure::TimedOut(_)])
+        ));
+        assert!(failed().after_retry(failed()).is_failed());
+    }
+}
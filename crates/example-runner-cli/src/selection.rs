@@ -0,0 +1,80 @@
+use globset::{Glob, GlobMatcher};
+
+use crate::{
+    execute_examples::ConfigurationNames,
+    filterset::{Filterset, FiltersetError},
+};
+
+/// Narrows the configurations that are run. Every kind of filter that is given has to match.
+#[derive(clap::Args, Debug)]
+pub struct SelectionArgs {
+    /// Only run configurations whose name (`<package>::examples::<example>::<label>`) contains one
+    /// of these
+    filters: Vec<String>,
+    /// Only run examples of these packages
+    #[arg(long = "package", value_name = "NAME")]
+    packages: Vec<String>,
+    /// Only run examples with a name matching one of these globs
+    #[arg(long = "example", value_name = "GLOB", value_parser = glob_matcher)]
+    examples: Vec<GlobMatcher>,
+    /// Only run configurations with a label matching one of these globs
+    #[arg(long = "label", value_name = "GLOB", value_parser = glob_matcher)]
+    labels: Vec<GlobMatcher>,
+    /// Skip configurations whose name contains any of these
+    #[arg(long, value_name = "FILTER")]
+    exclude: Vec<String>,
+    /// Only run configurations with one of these tags
+    #[arg(long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+    /// Skip configurations with any of these tags
+    #[arg(long = "skip-tag", value_name = "TAG")]
+    skip_tags: Vec<String>,
+    /// Only run configurations matching one of these filter expressions, e.g.
+    /// `package(core) & !example(bench_*)`
+    #[arg(long = "filter", value_name = "EXPRESSION")]
+    filtersets: Vec<String>,
+}
+
+/// The selection from the command line, with its filter expressions parsed
+pub struct Selection {
+    args: SelectionArgs,
+    filtersets: Vec<Filterset>,
+}
+
+fn glob_matcher(glob: &str) -> Result<GlobMatcher, globset::Error> {
+    Ok(Glob::new(glob)?.compile_matcher())
+}
+
+impl SelectionArgs {
+    pub fn into_selection(self) -> Result<Selection, FiltersetError> {
+        Ok(Selection {
+            filtersets: self
+                .filtersets
+                .iter()
+                .map(|expression| Filterset::parse(expression))
+                .collect::<Result<_, _>>()?,
+            args: self,
+        })
+    }
+}
+
+impl Selection {
+    pub fn matches(&self, names: &ConfigurationNames, tags: &[String]) -> bool {
+        let (package, example, label) = names;
+        let name = format!("{package}::examples::{example}::{label}");
+        let args = &self.args;
+
+        (args.filters.is_empty() || args.filters.iter().any(|filter| name.contains(filter)))
+            && (args.packages.is_empty() || args.packages.contains(package))
+            && (args.examples.is_empty() || args.examples.iter().any(|glob| glob.is_match(example)))
+            && (args.labels.is_empty() || args.labels.iter().any(|glob| glob.is_match(label)))
+            && !args.exclude.iter().any(|filter| name.contains(filter))
+            && (args.tags.is_empty() || args.tags.iter().any(|tag| tags.contains(tag)))
+            && !args.skip_tags.iter().any(|tag| tags.contains(tag))
+            && (self.filtersets.is_empty()
+                || self
+                    .filtersets
+                    .iter()
+                    .any(|filterset| filterset.matches(names, tags)))
+    }
+}
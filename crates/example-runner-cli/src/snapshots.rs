@@ -0,0 +1,180 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    execute_examples::{CapturedOutput, ConfigurationNames},
+    output_assertions::unified_diff,
+};
+
+pub enum SnapshotOutcome {
+    /// The output matches the stored snapshot
+    Matched,
+    /// The snapshot was written or updated with `--bless`
+    Blessed,
+    /// There is no snapshot yet and `--bless` wasn't passed
+    Missing,
+    /// The output drifted from the stored snapshot, with a diff from the snapshot to the output
+    Changed(String),
+}
+
+/// The snapshot file of a configuration: `<package>/examples/snapshots/<example>@<label>.snap`
+pub fn snapshot_path(manifest_dir: &Path, (_, example, label): &ConfigurationNames) -> PathBuf {
+    manifest_dir
+        .join("examples")
+        .join("snapshots")
+        .join(format!("{}@{}.snap", sanitize(example), sanitize(label)))
+}
+
+/// Labels are free text, so replace anything that might not be valid in a file name
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn render_snapshot(output: &CapturedOutput) -> String {
+    format!(
+        "----- stdout -----\n{}----- stderr -----\n{}",
+        output.stdout, output.stderr
+    )
+}
+
+/// Compares the output against the snapshot at `path`, or writes it there if `bless` is set
+pub async fn check_snapshot(
+    path: &Path,
+    output: &CapturedOutput,
+    bless: bool,
+) -> std::io::Result<SnapshotOutcome> {
+    let rendered = render_snapshot(output);
+
+    let stored = match tokio::fs::read_to_string(path).await {
+        Ok(stored) => Some(stored),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err),
+    };
+
+    match stored {
+        Some(stored) if stored == rendered => Ok(SnapshotOutcome::Matched),
+        _ if bless => {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(path, rendered).await?;
+            Ok(SnapshotOutcome::Blessed)
+        }
+        Some(stored) => Ok(SnapshotOutcome::Changed(unified_diff(&stored, &rendered))),
+        None => Ok(SnapshotOutcome::Missing),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(stdout: &str) -> CapturedOutput {
+        CapturedOutput {
+            stdout: stdout.to_owned(),
+            stderr: String::new(),
+        }
+    }
+
+    /// A directory of its own for every test, as tests run in parallel
+    fn temp_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "example-runner-snapshots-{}-{test}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn snapshots_are_named_after_example_and_label() {
+        let names = (
+            "demo".to_owned(),
+            "hello".to_owned(),
+            "explicit-0".to_owned(),
+        );
+
+        assert_eq!(
+            snapshot_path(Path::new("/work/demo"), &names),
+            Path::new("/work/demo/examples/snapshots/hello@explicit-0.snap")
+        );
+    }
+
+    #[test]
+    fn sanitize_replaces_unusual_characters() {
+        assert_eq!(sanitize("with spaces/and:colons"), "with_spaces_and_colons");
+        assert_eq!(sanitize("kebab-snake_dot.1"), "kebab-snake_dot.1");
+        assert_eq!(sanitize("ünicode"), "_nicode");
+    }
+
+    #[tokio::test]
+    async fn bless_writes_the_snapshot() {
+        let dir = temp_dir("bless");
+        let path = dir.join("snapshots").join("hello@default.snap");
+
+        assert!(matches!(
+            check_snapshot(&path, &output("hello\n"), false)
+                .await
+                .unwrap(),
+            SnapshotOutcome::Missing
+        ));
+        assert!(matches!(
+            check_snapshot(&path, &output("hello\n"), true)
+                .await
+                .unwrap(),
+            SnapshotOutcome::Blessed
+        ));
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "----- stdout -----\nhello\n----- stderr -----\n"
+        );
+        assert!(matches!(
+            check_snapshot(&path, &output("hello\n"), true)
+                .await
+                .unwrap(),
+            SnapshotOutcome::Matched
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn changed_output_is_diffed_until_blessed() {
+        let dir = temp_dir("changed");
+        let path = dir.join("hello@default.snap");
+        check_snapshot(&path, &output("hello\n"), true)
+            .await
+            .unwrap();
+
+        match check_snapshot(&path, &output("goodbye\n"), false)
+            .await
+            .unwrap()
+        {
+            SnapshotOutcome::Changed(diff) => {
+                assert!(diff.contains("-hello\n+goodbye\n"), "{diff}")
+            }
+            _ => panic!("expected the snapshot to change"),
+        }
+        assert!(matches!(
+            check_snapshot(&path, &output("goodbye\n"), true)
+                .await
+                .unwrap(),
+            SnapshotOutcome::Blessed
+        ));
+        assert!(matches!(
+            check_snapshot(&path, &output("goodbye\n"), false)
+                .await
+                .unwrap(),
+            SnapshotOutcome::Matched
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
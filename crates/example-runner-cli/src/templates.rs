@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use serde::Deserialize;
 
@@ -9,7 +9,10 @@ pub struct WorkspaceExampleRunnerConfig {
     #[serde(default)]
     pub templates: HashMap<String, ExampleConfig>,
     #[serde(default)]
-    pub default: HashSet<RunnerOptions>,
+    pub default: Vec<RunnerOptions>,
+    /// Never run examples of the workspace in parallel
+    #[serde(default)]
+    pub serial: bool,
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -19,5 +22,5 @@ pub struct PackageExampleRunnerConfig {
     #[serde(default)]
     pub extend_workspace_defaults: bool,
     #[serde(default)]
-    pub default: HashSet<RunnerOptions>,
+    pub default: Vec<RunnerOptions>,
 }
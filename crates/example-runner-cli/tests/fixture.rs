@@ -0,0 +1,137 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use serde_json::Value;
+
+/// A directory for the target directory and other files of a test
+fn files(test: &str) -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join(test)
+}
+
+/// Runs the example runner in the fixture workspace
+fn run_fixture(test: &str, args: &[&str]) -> (Output, PathBuf) {
+    let files = files(test);
+    // Leftovers of a previous run would change the outcome, e.g. the marker of the flaky example
+    let _ = std::fs::remove_dir_all(&files);
+    std::fs::create_dir_all(&files).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_example-runner-cli"))
+        .args(args)
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixture"))
+        .env("CARGO_TARGET_DIR", files.join("target"))
+        .env("FIXTURE_FLAKY_MARKER", files.join("flaky-marker"))
+        .env_remove("GITHUB_ACTIONS")
+        .env_remove("EXAMPLE_RUNNER_FIXTURE_UNSET")
+        .output()
+        .unwrap();
+
+    (output, files)
+}
+
+fn events(output: &Output) -> Vec<Value> {
+    String::from_utf8(output.stdout.clone())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn verdicts() {
+    let (output, files) = run_fixture(
+        "verdicts",
+        &[
+            "--message-format",
+            "json",
+            "--junit",
+            files("verdicts").join("junit.xml").to_str().unwrap(),
+        ],
+    );
+    assert!(!output.status.success());
+    let events = events(&output);
+
+    let verdicts: BTreeMap<String, (String, u64)> = events
+        .iter()
+        .filter(|event| event["event"] == "run-finished")
+        .map(|event| {
+            (
+                format!("{}::{}", event["example"], event["label"]).replace('"', ""),
+                (
+                    event["verdict"].as_str().unwrap().to_owned(),
+                    event["attempts"].as_u64().unwrap(),
+                ),
+            )
+        })
+        .collect();
+    let expected = [
+        ("exit_code::expected-exit-code", "passed", 1),
+        // `xfail` configurations aren't retried
+        ("exit_code::expected-failure", "expected-failure", 1),
+        ("exit_code::fails", "failed", 1),
+        ("flaky::retried", "flaky", 2),
+        ("greeting::passes", "passed", 1),
+        ("greeting::skipped", "skipped", 1),
+        ("greeting::wrong-output", "failed", 1),
+        ("ignored::explicit-0", "ignored", 1),
+    ]
+    .map(|(name, verdict, attempts)| (name.to_owned(), (verdict.to_owned(), attempts)));
+    assert_eq!(verdicts, BTreeMap::from(expected));
+
+    let finished = events.last().unwrap();
+    assert_eq!(finished["event"], "finished");
+    for (key, count) in [
+        ("passed", 2),
+        ("failed", 2),
+        ("ignored", 1),
+        ("skipped", 1),
+        ("flaky", 1),
+        ("expected_failures", 1),
+        ("not_run", 0),
+    ] {
+        assert_eq!(finished[key], count, "{key}");
+    }
+
+    let junit = std::fs::read_to_string(files.join("junit.xml")).unwrap();
+    assert!(junit
+        .contains("<testsuites name=\"example-runner\" tests=\"8\" failures=\"2\" skipped=\"3\""));
+    assert!(junit.contains("message=\"expected success, got exit code 3\""));
+    assert!(junit.contains("<flakyFailure message=\"expected success, got exit code 1\""));
+}
+
+#[test]
+fn fail_fast_cancels_the_remaining_runs() {
+    let (output, _) = run_fixture(
+        "fail_fast",
+        &["--message-format", "json", "--fail-fast", "exit_code"],
+    );
+    assert!(!output.status.success());
+
+    let verdicts: Vec<String> = events(&output)
+        .iter()
+        .filter(|event| event["event"] == "run-finished")
+        .map(|event| format!("{} {}", event["label"], event["verdict"]).replace('"', ""))
+        .collect();
+    assert_eq!(
+        verdicts,
+        [
+            "fails failed",
+            "expected-exit-code not-run",
+            "expected-failure not-run",
+        ]
+    );
+}
+
+#[test]
+fn tap_starts_with_the_version() {
+    let (output, _) = run_fixture("tap", &["--format", "tap", "--label", "passes"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines();
+    assert_eq!(lines.next(), Some("TAP version 13"));
+    assert_eq!(lines.next(), Some("1..1"));
+    assert!(lines.next().unwrap().starts_with("ok 1 "));
+}
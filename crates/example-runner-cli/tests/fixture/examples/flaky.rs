@@ -0,0 +1,8 @@
+/// Fails the first time, creating the marker file so the next attempt passes
+fn main() {
+    let marker = std::env::var_os("FIXTURE_FLAKY_MARKER").expect("the marker path is set");
+    if std::fs::metadata(&marker).is_err() {
+        std::fs::write(&marker, "").unwrap();
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    unreachable!("the example is ignored");
+}
@@ -1,5 +1,6 @@
 use super::path::ExamplePath;
 
+#[allow(dead_code)]
 pub struct Example<'a> {
     path: ExamplePath<'a>,
 }
@@ -2,6 +2,7 @@ use std::borrow::Cow;
 
 use cargo_metadata::{PackageId, Target};
 
+#[allow(dead_code)]
 pub struct ExamplePath<'a> {
     package_id: PackageId,
     target: Cow<'a, Target>,
@@ -0,0 +1,246 @@
+mod parse;
+
+use std::fmt::Display;
+
+use miette::{Diagnostic, SourceSpan};
+use nom::{
+    error::{VerboseError, VerboseErrorKind},
+    Finish,
+};
+
+use crate::span::visible_span;
+
+/// A single configuration option like `unix` or `target_os = "linux"`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Cfg {
+    Name(String),
+    KeyValue(String, String),
+}
+
+/// An expression as used in `#[cfg(..)]` or `[target.'cfg(..)'.dependencies]`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CfgExpr {
+    Predicate(Cfg),
+    All(Vec<CfgExpr>),
+    Any(Vec<CfgExpr>),
+    Not(Box<CfgExpr>),
+}
+
+#[derive(thiserror::Error, Debug, Diagnostic)]
+#[diagnostic(
+    code(rust_metadata::cfg::parse),
+    help(
+        "cfg expressions look like `cfg(all(unix, target_pointer_width = \"64\"))`, combining options with `all(..)`, `any(..)` and `not(..)`"
+    )
+)]
+#[error("Invalid cfg expression")]
+pub struct CfgParseError {
+    #[source_code]
+    input: String,
+    #[label("{reason}")]
+    span: SourceSpan,
+    reason: String,
+}
+
+impl CfgParseError {
+    fn new(input: &str, error: VerboseError<&str>) -> Self {
+        // The first error is the innermost one, the contexts added around it describe what failed
+        let offset = error
+            .errors
+            .first()
+            .map(|(rest, _)| input.len() - rest.len())
+            .unwrap_or_default();
+        let reason = error
+            .errors
+            .iter()
+            .find_map(|(_, kind)| match kind {
+                VerboseErrorKind::Context(context) => Some(*context),
+                _ => None,
+            })
+            .unwrap_or("invalid syntax");
+
+        let length = input[offset..].chars().next().map_or(0, char::len_utf8);
+
+        Self {
+            input: input.to_owned(),
+            span: visible_span(input, offset, length),
+            reason: reason.to_owned(),
+        }
+    }
+
+    /// What is wrong at the location of the error
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl CfgExpr {
+    /// Parses an expression including the surrounding `cfg(..)`
+    pub fn parse(input: &str) -> Result<Self, CfgParseError> {
+        parse::cfg_expr(input)
+            .finish()
+            .map(|(_, expr)| expr)
+            .map_err(|error| CfgParseError::new(input, error))
+    }
+
+    /// Evaluates the expression against the set options of a target
+    pub fn matches(&self, cfgs: &[Cfg]) -> bool {
+        match self {
+            CfgExpr::Predicate(cfg) => cfgs.contains(cfg),
+            CfgExpr::All(exprs) => exprs.iter().all(|expr| expr.matches(cfgs)),
+            CfgExpr::Any(exprs) => exprs.iter().any(|expr| expr.matches(cfgs)),
+            CfgExpr::Not(expr) => !expr.matches(cfgs),
+        }
+    }
+}
+
+/// Parses the options of a target as printed by `rustc --print cfg`, one per line
+pub fn parse_cfgs(output: &str) -> Result<Vec<Cfg>, CfgParseError> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            parse::cfg_line(line)
+                .finish()
+                .map(|(_, cfg)| cfg)
+                .map_err(|error| CfgParseError::new(line, error))
+        })
+        .collect()
+}
+
+impl Display for Cfg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cfg::Name(name) => write!(f, "{name}"),
+            Cfg::KeyValue(key, value) => write!(f, "{key} = \"{value}\""),
+        }
+    }
+}
+
+impl Display for CfgExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (operator, exprs) = match self {
+            CfgExpr::Predicate(cfg) => return write!(f, "{cfg}"),
+            CfgExpr::Not(expr) => return write!(f, "not({expr})"),
+            CfgExpr::All(exprs) => ("all", exprs),
+            CfgExpr::Any(exprs) => ("any", exprs),
+        };
+
+        write!(f, "{operator}(")?;
+        for (index, expr) in exprs.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{expr}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(name: &str) -> CfgExpr {
+        CfgExpr::Predicate(Cfg::Name(name.to_owned()))
+    }
+
+    fn key_value(key: &str, value: &str) -> CfgExpr {
+        CfgExpr::Predicate(Cfg::KeyValue(key.to_owned(), value.to_owned()))
+    }
+
+    fn linux_64() -> Vec<Cfg> {
+        parse_cfgs("unix\ntarget_os=\"linux\"\ntarget_pointer_width=\"64\"\n").unwrap()
+    }
+
+    #[test]
+    fn parses_expressions() {
+        assert_eq!(CfgExpr::parse("cfg(unix)").unwrap(), name("unix"));
+        assert_eq!(
+            CfgExpr::parse("cfg(target_os = \"linux\")").unwrap(),
+            key_value("target_os", "linux")
+        );
+        assert_eq!(
+            CfgExpr::parse(" cfg( all( unix , not(windows), any(), ) ) ").unwrap(),
+            CfgExpr::All(vec![
+                name("unix"),
+                CfgExpr::Not(Box::new(name("windows"))),
+                CfgExpr::Any(vec![]),
+            ])
+        );
+        // Operator names without arguments are plain options
+        assert_eq!(CfgExpr::parse("cfg(all)").unwrap(), name("all"));
+    }
+
+    #[test]
+    fn displays_parsable_expressions() {
+        let expression = "all(unix, any(target_os = \"linux\", not(windows)))";
+        let parsed = CfgExpr::parse(&format!("cfg({expression})")).unwrap();
+
+        assert_eq!(parsed.to_string(), expression);
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for (input, reason, offset) in [
+            ("unix", "expected `cfg(..)`", 0),
+            ("cfg unix", "expected `(`", 4),
+            (
+                "cfg()",
+                "expected a cfg option like `unix` or `target_os = \"linux\"`",
+                4,
+            ),
+            (
+                "cfg(target_os = linux)",
+                "expected a string like `\"linux\"`",
+                16,
+            ),
+            (
+                "cfg(target_os = \"linux)",
+                "unclosed string, expected a closing `\"`",
+                22,
+            ),
+            ("cfg(all(unix windows))", "expected `,` or `)`", 13),
+            ("cfg(not(unix, windows))", "expected `)`", 12),
+            ("cfg(unix) unix", "expected the end of the expression", 10),
+        ] {
+            let error = CfgExpr::parse(input).unwrap_err();
+            assert_eq!(error.reason(), reason, "{input}");
+            assert_eq!(error.span.offset(), offset, "{input}");
+        }
+    }
+
+    #[test]
+    fn parses_rustc_output() {
+        assert_eq!(
+            linux_64(),
+            vec![
+                Cfg::Name("unix".to_owned()),
+                Cfg::KeyValue("target_os".to_owned(), "linux".to_owned()),
+                Cfg::KeyValue("target_pointer_width".to_owned(), "64".to_owned()),
+            ]
+        );
+        assert_eq!(
+            parse_cfgs("unix windows").unwrap_err().reason(),
+            "expected the end of the line"
+        );
+    }
+
+    #[test]
+    fn evaluates_expressions() {
+        let cfgs = linux_64();
+        let matches = |expression: &str| CfgExpr::parse(expression).unwrap().matches(&cfgs);
+
+        assert!(matches("cfg(unix)"));
+        assert!(!matches("cfg(windows)"));
+        assert!(matches("cfg(target_os = \"linux\")"));
+        assert!(!matches("cfg(target_os = \"macos\")"));
+        assert!(matches("cfg(all(unix, target_pointer_width = \"64\"))"));
+        assert!(!matches("cfg(all(unix, windows))"));
+        assert!(matches("cfg(any(windows, unix))"));
+        assert!(matches("cfg(not(windows))"));
+        // Like in rustc, `all()` is true and `any()` is false
+        assert!(matches("cfg(all())"));
+        assert!(!matches("cfg(any())"));
+    }
+}
@@ -0,0 +1,104 @@
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till},
+    character::complete::{alpha1, alphanumeric1, char, multispace0},
+    combinator::{cut, eof, map, opt, peek, recognize},
+    error::{context, VerboseError},
+    multi::{many0_count, separated_list0},
+    sequence::{delimited, pair, preceded, terminated},
+    IResult,
+};
+
+use super::{Cfg, CfgExpr};
+
+type ParseResult<'a, T> = IResult<&'a str, T, VerboseError<&'a str>>;
+
+/// Skips whitespace in front of the parser
+fn ws<'a, T>(
+    parser: impl FnMut(&'a str) -> ParseResult<'a, T>,
+) -> impl FnMut(&'a str) -> ParseResult<'a, T> {
+    preceded(multispace0, parser)
+}
+
+fn identifier(input: &str) -> ParseResult<'_, &str> {
+    recognize(pair(
+        alt((alpha1, tag("_"))),
+        many0_count(alt((alphanumeric1, tag("_")))),
+    ))(input)
+}
+
+fn string(input: &str) -> ParseResult<'_, &str> {
+    delimited(
+        char('"'),
+        take_till(|c| c == '"'),
+        context("unclosed string, expected a closing `\"`", char('"')),
+    )(input)
+}
+
+fn open(input: &str) -> ParseResult<'_, char> {
+    ws(context("expected `(`", char('(')))(input)
+}
+
+fn close(input: &str) -> ParseResult<'_, char> {
+    ws(context("expected `)`", char(')')))(input)
+}
+
+/// `name` or `key = "value"`
+fn cfg(input: &str) -> ParseResult<'_, Cfg> {
+    let (input, name) = ws(context(
+        "expected a cfg option like `unix` or `target_os = \"linux\"`",
+        identifier,
+    ))(input)?;
+    let (input, value) = opt(preceded(
+        ws(char('=')),
+        cut(ws(context("expected a string like `\"linux\"`", string))),
+    ))(input)?;
+
+    let cfg = match value {
+        Some(value) => Cfg::KeyValue(name.to_owned(), value.to_owned()),
+        None => Cfg::Name(name.to_owned()),
+    };
+    Ok((input, cfg))
+}
+
+/// The name of an operator, only if it is followed by its arguments
+fn operator<'a>(name: &'static str) -> impl FnMut(&'a str) -> ParseResult<'a, &'a str> {
+    ws(terminated(tag(name), peek(ws(char('(')))))
+}
+
+/// The arguments of `all(..)` and `any(..)`, allowing a trailing comma
+fn list(input: &str) -> ParseResult<'_, Vec<CfgExpr>> {
+    delimited(
+        open,
+        terminated(separated_list0(ws(char(',')), expr), opt(ws(char(',')))),
+        ws(context("expected `,` or `)`", char(')'))),
+    )(input)
+}
+
+fn expr(input: &str) -> ParseResult<'_, CfgExpr> {
+    alt((
+        map(preceded(operator("all"), cut(list)), CfgExpr::All),
+        map(preceded(operator("any"), cut(list)), CfgExpr::Any),
+        map(
+            preceded(operator("not"), cut(delimited(open, expr, close))),
+            |expr| CfgExpr::Not(Box::new(expr)),
+        ),
+        map(cfg, CfgExpr::Predicate),
+    ))(input)
+}
+
+/// A whole `cfg(..)` expression
+pub fn cfg_expr(input: &str) -> ParseResult<'_, CfgExpr> {
+    terminated(
+        preceded(
+            ws(context("expected `cfg(..)`", tag("cfg"))),
+            cut(delimited(open, expr, close)),
+        ),
+        cut(context("expected the end of the expression", ws(eof))),
+    )(input)
+}
+
+/// A line of `rustc --print cfg`
+pub fn cfg_line(input: &str) -> ParseResult<'_, Cfg> {
+    terminated(cfg, cut(context("expected the end of the line", ws(eof))))(input)
+}
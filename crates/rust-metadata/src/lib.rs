@@ -1 +1,3 @@
 pub mod cargo;
+pub mod cfg;
+pub mod span;
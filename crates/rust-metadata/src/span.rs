@@ -0,0 +1,12 @@
+use miette::SourceSpan;
+
+/// The span of an error in `input`, moved onto the last character if it is empty and at the end,
+/// as empty spans at the end aren't rendered
+pub fn visible_span(input: &str, offset: usize, length: usize) -> SourceSpan {
+    match input[..offset].chars().last() {
+        Some(last) if length == 0 && offset == input.len() => {
+            (offset - last.len_utf8(), last.len_utf8()).into()
+        }
+        _ => (offset, length).into(),
+    }
+}